@@ -0,0 +1,170 @@
+//! Runs the same transforms as the GUI on image files, e.g.
+//!
+//! ```text
+//! dip-cli --input in.png --output out.png --op color/hist_equalize --op filter/gaussian=5,1.2
+//! ```
+
+use dip_core::io::{read_working_image, write_image};
+use dip_core::transform::registry::{Args, Output, ParamKind, ParamSpec, Registry};
+use dip_core::transform::set_threads;
+use std::error::Error;
+
+const USAGE: &str = "\
+Usage: dip-cli --input <path> --output <path> [--img2 <path>] --op <transform>[=<args>]...
+       dip-cli --list
+
+Options:
+  --input <path>    Image to process
+  --output <path>   Where to save the result (.png, .tif, .jpg or .bmp)
+  --img2 <path>     Second image for binary transforms such as binary_op/add,
+                    or the kernel or template of fft/convolve and fft/correlate
+  --op <op>         Transform to apply, with comma-separated arguments in the
+                    order given by --list. Empty arguments take the default.
+                    May be repeated; steps run in order. Anything a step
+                    measures, such as the shift found by fft/register, is
+                    printed. After fft/spectrum, the fft/filter, fft/notch
+                    and fft/idft steps work on the complex spectrum rather
+                    than its picture.
+  --threads <n>     Number of threads to use, all cores by default and
+                    at most one per core
+  --list            List the available transforms and their arguments";
+
+#[derive(Default)]
+struct Options {
+    input: Option<String>,
+    output: Option<String>,
+    img2: Option<String>,
+    ops: Vec<String>,
+    threads: Option<usize>,
+    list: bool,
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("Missing value for {}", arg))
+        };
+        match arg.as_str() {
+            "--input" => options.input = Some(value()?),
+            "--output" => options.output = Some(value()?),
+            "--img2" => options.img2 = Some(value()?),
+            "--op" => options.ops.push(value()?),
+            "--threads" => {
+                let threads = value()?;
+                match threads.parse() {
+                    Ok(threads) if threads > 0 => options.threads = Some(threads),
+                    _ => return Err(format!("Invalid thread count \"{}\"", threads)),
+                }
+            }
+            "--list" => options.list = true,
+            "--help" | "-h" => return Err(String::new()),
+            _ => return Err(format!("Unexpected argument \"{}\"", arg)),
+        }
+    }
+    Ok(options)
+}
+
+fn describe_param(param: &ParamSpec) -> String {
+    let kind = match &param.kind {
+        ParamKind::Int { min, max } => format!("int {}..{}", min, max),
+        ParamKind::Float { min, max } => format!("float {}..{}", min, max),
+        ParamKind::Choice { options } => options.join("|"),
+        ParamKind::Matrix => "matrix, rows separated by ;".to_string(),
+    };
+    match &param.default {
+        Some(default) => format!("{}={} ({})", param.name, default, kind),
+        None => format!("<{}> ({})", param.name, kind),
+    }
+}
+
+fn list(registry: &Registry) {
+    for transform in registry.list() {
+        let mut line = transform.name.to_string();
+        if transform.second_image {
+            line.push_str(" [--img2]");
+        }
+        for param in &transform.params {
+            line.push_str("  ");
+            line.push_str(&describe_param(param));
+        }
+        println!("{}", line);
+    }
+}
+
+fn run(options: Options) -> Result<(), Box<dyn Error>> {
+    let registry = Registry::new();
+    if let Some(threads) = options.threads {
+        set_threads(threads);
+    }
+    if options.list {
+        list(&registry);
+        return Ok(());
+    }
+
+    let input = options.input.ok_or("Missing --input")?;
+    let output = options.output.ok_or("Missing --output")?;
+    if options.ops.is_empty() {
+        return Err("At least one --op is required".into());
+    }
+
+    // Validate every step before spending time on any of them
+    let mut steps = vec![];
+    for op in &options.ops {
+        let (name, arg) = op.split_once('=').unwrap_or((op, ""));
+        let transform = registry.get(name)?;
+        let args = Args::from_positional(transform.params(), arg)
+            .map_err(|e| format!("Invalid arguments for {}: {}", name, e))?;
+        if transform.needs_second_image() && options.img2.is_none() {
+            return Err(format!("{} requires --img2", name).into());
+        }
+        steps.push((transform, args));
+    }
+
+    // Carries the spectrum between steps, so the inverse of a filtered
+    // spectrum is exact
+    let mut state = Output::from(read_working_image(&input)?);
+    let img2 = options
+        .img2
+        .as_deref()
+        .map(read_working_image)
+        .transpose()?;
+    for (transform, args) in steps {
+        let output = transform.apply_with_spectrum(
+            &state.image,
+            state.spectrum.as_ref(),
+            img2.as_ref(),
+            &args,
+        )?;
+        if !output.measurements.is_empty() {
+            let measurements: Vec<String> = output
+                .measurements
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect();
+            println!("{}: {}", transform.name(), measurements.join(" "));
+        }
+        state = output;
+    }
+    write_image(&state.image, &output)?;
+    Ok(())
+}
+
+fn main() {
+    let options = match parse_options(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) if e.is_empty() => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = run(options) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,85 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DipError {
+    /// The file could not be read or written.
+    Io(String),
+    /// The data is not an image in a supported format.
+    Decode(String),
+    Encode(String),
+    UnknownTransform(String),
+    InvalidArgument(String),
+    /// A binary transform was called without its second image.
+    MissingSecondImage(String),
+    /// Two images that must have the same size do not.
+    DimensionMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    /// A contour-based transform found nothing to trace.
+    EmptyContour,
+    /// An image, history or path that the caller referred to does not exist.
+    NotFound(String),
+    NothingToUndo,
+    NothingToRedo,
+    /// The transform was stopped before it finished.
+    Cancelled,
+}
+
+impl DipError {
+    /// Stable identifier for the frontend to match on, unlike the message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            DipError::Io(_) => "io",
+            DipError::Decode(_) => "decode",
+            DipError::Encode(_) => "encode",
+            DipError::UnknownTransform(_) => "unknown_transform",
+            DipError::InvalidArgument(_) => "invalid_argument",
+            DipError::MissingSecondImage(_) => "missing_second_image",
+            DipError::DimensionMismatch { .. } => "dimension_mismatch",
+            DipError::EmptyContour => "empty_contour",
+            DipError::NotFound(_) => "not_found",
+            DipError::NothingToUndo => "nothing_to_undo",
+            DipError::NothingToRedo => "nothing_to_redo",
+            DipError::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl fmt::Display for DipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DipError::Io(e) => write!(f, "{}", e),
+            DipError::Decode(e) => write!(f, "Failed to decode image: {}", e),
+            DipError::Encode(e) => write!(f, "Failed to encode image: {}", e),
+            DipError::UnknownTransform(name) => write!(f, "Invalid transform \"{}\"", name),
+            DipError::InvalidArgument(e) => write!(f, "{}", e),
+            DipError::MissingSecondImage(name) => {
+                write!(f, "Transform \"{}\" requires a second image", name)
+            }
+            DipError::DimensionMismatch { expected, actual } => write!(
+                f,
+                "Expected a {}x{} image, got {}x{}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+            DipError::EmptyContour => write!(f, "No contour found in the image"),
+            DipError::NotFound(e) => write!(f, "{}", e),
+            DipError::NothingToUndo => write!(f, "Nothing to undo"),
+            DipError::NothingToRedo => write!(f, "Nothing to redo"),
+            DipError::Cancelled => write!(f, "The transform was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for DipError {}
+
+/// Serialized as `{ "code": ..., "message": ... }`.
+impl Serialize for DipError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("DipError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
@@ -0,0 +1,110 @@
+use image::io::Reader as ImageReader;
+use image::{ColorType, DynamicImage, ImageFormat};
+use std::borrow::Cow;
+use std::io::{BufWriter, Cursor};
+use std::path::Path;
+
+use crate::error::DipError;
+
+pub fn read_image(path: &str) -> Result<DynamicImage, DipError> {
+    let img = ImageReader::open(path)
+        .map_err(|e| DipError::Io(format!("Failed to open image: {}", e)))?
+        .with_guessed_format()
+        .map_err(|e| DipError::Io(format!("Failed to guess image format: {}", e)))?
+        .decode()
+        .map_err(|e| DipError::Decode(e.to_string()))?;
+    Ok(img)
+}
+
+/// Reads an image the way the GUI opens it, so that the CLI and replayed
+/// histories start from exactly the same pixels.
+///
+/// 8-bit images are opened as RGB8. Deeper ones keep their precision as
+/// Luma16, RGB16 or RGB32F. Alpha is dropped either way.
+pub fn read_working_image(path: &str) -> Result<DynamicImage, DipError> {
+    let img = read_image(path)?;
+    Ok(match img.color() {
+        ColorType::L16 | ColorType::La16 => DynamicImage::ImageLuma16(img.to_luma16()),
+        ColorType::Rgb16 | ColorType::Rgba16 => DynamicImage::ImageRgb16(img.to_rgb16()),
+        ColorType::Rgb32F | ColorType::Rgba32F => DynamicImage::ImageRgb32F(img.to_rgb32f()),
+        _ => DynamicImage::ImageRgb8(img.to_rgb8()),
+    })
+}
+
+/// `img` with 8-bit samples, as needed for display and by most formats.
+pub fn to_8bit(img: &DynamicImage) -> Cow<'_, DynamicImage> {
+    let color = img.color();
+    if color.bytes_per_pixel() == color.channel_count() {
+        return Cow::Borrowed(img);
+    }
+    Cow::Owned(match (color.has_color(), color.has_alpha()) {
+        (false, false) => DynamicImage::ImageLuma8(img.to_luma8()),
+        (false, true) => DynamicImage::ImageLumaA8(img.to_luma_alpha8()),
+        (true, false) => DynamicImage::ImageRgb8(img.to_rgb8()),
+        (true, true) => DynamicImage::ImageRgba8(img.to_rgba8()),
+    })
+}
+
+/// `img` in a colour type that `format` can encode, keeping as much precision
+/// as the format allows.
+fn encodable(img: &DynamicImage, format: ImageFormat) -> Cow<'_, DynamicImage> {
+    let color = img.color();
+    match (format, color) {
+        (ImageFormat::Png | ImageFormat::Tiff, ColorType::Rgb32F) => {
+            Cow::Owned(DynamicImage::ImageRgb16(img.to_rgb16()))
+        }
+        (ImageFormat::Png | ImageFormat::Tiff, ColorType::Rgba32F) => {
+            Cow::Owned(DynamicImage::ImageRgba16(img.to_rgba16()))
+        }
+        // TIFF can't store gray with alpha
+        (ImageFormat::Tiff, ColorType::La8) => Cow::Owned(DynamicImage::ImageRgba8(img.to_rgba8())),
+        (ImageFormat::Tiff, ColorType::La16) => {
+            Cow::Owned(DynamicImage::ImageRgba16(img.to_rgba16()))
+        }
+        (ImageFormat::Png | ImageFormat::Tiff, _) => Cow::Borrowed(img),
+        // JPEG has no alpha either
+        (ImageFormat::Jpeg, _) if color.has_alpha() => {
+            Cow::Owned(to_8bit(&DynamicImage::ImageRgb32F(img.to_rgb32f())).into_owned())
+        }
+        _ => to_8bit(img),
+    }
+}
+
+/// Encodes the 8-bit version of `img` for display.
+pub fn encode_image_to_bmp(img: &DynamicImage) -> Result<Vec<u8>, DipError> {
+    let mut buf = Vec::new();
+    {
+        let mut writer = BufWriter::new(Cursor::new(&mut buf));
+        to_8bit(img)
+            .write_to(&mut writer, ImageFormat::Bmp)
+            .map_err(|e| DipError::Encode(e.to_string()))?;
+    }
+    Ok(buf)
+}
+
+/// Saves `img`, choosing the format from the extension of `path`. PNG and
+/// TIFF keep 16-bit samples, and store float images as 16-bit.
+pub fn write_image(img: &DynamicImage, path: &str) -> Result<(), DipError> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let format = match extension.as_deref() {
+        Some("jpg" | "jpeg") => ImageFormat::Jpeg,
+        Some("png") => ImageFormat::Png,
+        Some("bmp") => ImageFormat::Bmp,
+        Some("tif" | "tiff") => ImageFormat::Tiff,
+        _ => {
+            return Err(DipError::Encode(format!(
+                "Unsupported image format \"{}\"",
+                path
+            )))
+        }
+    };
+    encodable(img, format)
+        .save_with_format(path, format)
+        .map_err(|e| match e {
+            image::ImageError::IoError(e) => DipError::Io(format!("Failed to save image: {}", e)),
+            e => DipError::Encode(e.to_string()),
+        })
+}
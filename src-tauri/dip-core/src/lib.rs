@@ -0,0 +1,10 @@
+//! Image processing algorithms of the DIP app, independent of Tauri.
+//!
+//! Transforms are looked up by name in a [`transform::registry::Registry`],
+//! which is what the GUI, `dip-cli` and any other frontend should go through.
+
+pub mod error;
+pub mod io;
+pub mod transform;
+
+pub use error::DipError;
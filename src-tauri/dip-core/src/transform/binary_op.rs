@@ -0,0 +1,84 @@
+use image::{DynamicImage, Pixel};
+
+use crate::transform::registry::Registry;
+use crate::transform::utils::{get_pixel_rgb, max_dimensions, par_rows, Depth, RgbBuffer, Sample};
+
+/// Combines the images channel by channel, with samples on the `0..=T::MAX`
+/// scale. The smaller image is padded with black.
+fn combine<T, F>(img1: &RgbBuffer<T>, img2: &RgbBuffer<T>, f: F) -> RgbBuffer<T>
+where
+    T: Sample,
+    F: Fn(f64, f64) -> f64 + Sync,
+{
+    let (width, height) = max_dimensions(img1, img2);
+    let mut result = RgbBuffer::new(width, height);
+    par_rows(&mut result, width as usize * 3, |y, row| {
+        for (x, pixel) in row.chunks_mut(3).enumerate() {
+            let p1 = get_pixel_rgb(img1, x as u32, y as u32);
+            let p2 = get_pixel_rgb(img2, x as u32, y as u32);
+            let (p1, p2) = (p1.channels(), p2.channels());
+            for (c, value) in pixel.iter_mut().enumerate() {
+                *value = T::from_f64(f(p1[c].into_f64(), p2[c].into_f64()));
+            }
+        }
+    });
+    result
+}
+
+fn add<T: Sample>(img1: &RgbBuffer<T>, img2: &RgbBuffer<T>) -> RgbBuffer<T> {
+    combine(img1, img2, |a, b| a + b)
+}
+
+fn sub<T: Sample>(img1: &RgbBuffer<T>, img2: &RgbBuffer<T>) -> RgbBuffer<T> {
+    combine(img1, img2, |a, b| a - b)
+}
+
+fn mul<T: Sample>(img1: &RgbBuffer<T>, img2: &RgbBuffer<T>) -> RgbBuffer<T> {
+    combine(img1, img2, |a, b| a * b / T::MAX)
+}
+
+fn div<T: Sample>(img1: &RgbBuffer<T>, img2: &RgbBuffer<T>) -> RgbBuffer<T> {
+    combine(
+        img1,
+        img2,
+        |a, b| if b != 0.0 { a * T::MAX / b } else { T::MAX },
+    )
+}
+
+fn binary_op_impl<T: Sample>(img1: &RgbBuffer<T>, img2: &RgbBuffer<T>, op: &str) -> RgbBuffer<T> {
+    match op {
+        "add" => add(img1, img2),
+        "sub" => sub(img1, img2),
+        "mul" => mul(img1, img2),
+        "div" => div(img1, img2),
+        _ => img1.clone(),
+    }
+}
+
+/// The result has the bit depth of the deeper of the two images.
+pub fn binary_op(img1: &DynamicImage, img2: &DynamicImage, op: &str) -> DynamicImage {
+    match Depth::of(img1).max(Depth::of(img2)) {
+        Depth::U8 => DynamicImage::ImageRgb8(binary_op_impl(&img1.to_rgb8(), &img2.to_rgb8(), op)),
+        Depth::U16 => {
+            DynamicImage::ImageRgb16(binary_op_impl(&img1.to_rgb16(), &img2.to_rgb16(), op))
+        }
+        Depth::F32 => {
+            DynamicImage::ImageRgb32F(binary_op_impl(&img1.to_rgb32f(), &img2.to_rgb32f(), op))
+        }
+    }
+}
+
+pub(crate) fn register(registry: &mut Registry) {
+    registry.add_binary("binary_op/add", vec![], |img, img2, _| {
+        Ok(binary_op(img, img2, "add"))
+    });
+    registry.add_binary("binary_op/sub", vec![], |img, img2, _| {
+        Ok(binary_op(img, img2, "sub"))
+    });
+    registry.add_binary("binary_op/mul", vec![], |img, img2, _| {
+        Ok(binary_op(img, img2, "mul"))
+    });
+    registry.add_binary("binary_op/div", vec![], |img, img2, _| {
+        Ok(binary_op(img, img2, "div"))
+    });
+}
@@ -0,0 +1,131 @@
+use crate::transform::registry::{Args, ParamSpec};
+
+/// How pixels outside of the image are made up by neighbourhood operations.
+///
+/// With `abcd` as a row of the image, padding looks like:
+///
+/// ```text
+/// Constant    000|abcd|000
+/// Replicate   aaa|abcd|ddd
+/// Reflect     cba|abcd|dcb
+/// Reflect101  dcb|abcd|cba
+/// Wrap        bcd|abcd|abc
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BorderMode {
+    /// Black, or whatever the caller fills with.
+    Constant,
+    Replicate,
+    /// Mirrored, repeating the edge pixel.
+    Reflect,
+    /// Mirrored around the edge pixel, without repeating it.
+    Reflect101,
+    Wrap,
+}
+
+impl Default for BorderMode {
+    fn default() -> Self {
+        BorderMode::Reflect101
+    }
+}
+
+impl BorderMode {
+    pub const NAMES: [&'static str; 5] =
+        ["constant", "replicate", "reflect", "reflect_101", "wrap"];
+
+    pub fn from_name(name: &str) -> Option<BorderMode> {
+        match name {
+            "constant" => Some(BorderMode::Constant),
+            "replicate" => Some(BorderMode::Replicate),
+            "reflect" => Some(BorderMode::Reflect),
+            "reflect_101" => Some(BorderMode::Reflect101),
+            "wrap" => Some(BorderMode::Wrap),
+            _ => None,
+        }
+    }
+
+    /// Maps `i` onto `0..len`, or `None` where the constant is used instead.
+    pub fn index(self, i: i64, len: u32) -> Option<u32> {
+        let n = len as i64;
+        if (0..n).contains(&i) {
+            return Some(i as u32);
+        }
+        if n == 0 {
+            return None;
+        }
+        let i = match self {
+            BorderMode::Constant => return None,
+            BorderMode::Replicate => i.clamp(0, n - 1),
+            BorderMode::Reflect => {
+                let i = i.rem_euclid(2 * n);
+                if i < n {
+                    i
+                } else {
+                    2 * n - 1 - i
+                }
+            }
+            BorderMode::Reflect101 if n == 1 => 0,
+            BorderMode::Reflect101 => {
+                let period = 2 * n - 2;
+                let i = i.rem_euclid(period);
+                if i < n {
+                    i
+                } else {
+                    period - i
+                }
+            }
+            BorderMode::Wrap => i.rem_euclid(n),
+        };
+        Some(i as u32)
+    }
+
+    /// `index` for both coordinates of an image of the given size.
+    pub fn locate(self, x: i64, y: i64, (width, height): (u32, u32)) -> Option<(u32, u32)> {
+        Some((self.index(x, width)?, self.index(y, height)?))
+    }
+}
+
+pub(crate) fn border_param() -> ParamSpec {
+    ParamSpec::choice("border", &BorderMode::NAMES, Some("reflect_101"))
+}
+
+pub(crate) fn border(args: &Args) -> BorderMode {
+    BorderMode::from_name(args.str("border")).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pad(mode: BorderMode) -> String {
+        (-3..7)
+            .map(|i| match mode.index(i, 4) {
+                Some(i) => (b'a' + i as u8) as char,
+                None => '0',
+            })
+            .collect()
+    }
+
+    #[test]
+    fn padding_matches_the_documentation() {
+        assert_eq!(pad(BorderMode::Constant), "000abcd000");
+        assert_eq!(pad(BorderMode::Replicate), "aaaabcdddd");
+        assert_eq!(pad(BorderMode::Reflect), "cbaabcddcb");
+        assert_eq!(pad(BorderMode::Reflect101), "dcbabcdcba");
+        assert_eq!(pad(BorderMode::Wrap), "bcdabcdabc");
+    }
+
+    #[test]
+    fn far_away_pixels_stay_in_bounds() {
+        for name in BorderMode::NAMES {
+            let mode = BorderMode::from_name(name).unwrap();
+            for len in 1..5 {
+                for i in -20..20 {
+                    if let Some(j) = mode.index(i, len) {
+                        assert!(j < len, "{} mapped {} to {} of {}", name, i, j, len);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use image::{DynamicImage, ImageBuffer, Pixel};
+use imageproc;
+
+use crate::transform::registry::{ParamSpec, Registry};
+use crate::transform::utils::{gray, gray_to_rgb32f, map_buffer, map_gray, Depth, Plane, Sample};
+
+pub fn to_gray(img: DynamicImage) -> DynamicImage {
+    map_gray!(&img, |plane| plane.clone())
+}
+
+pub fn to_binary(img: DynamicImage, threshold: Option<f32>) -> DynamicImage {
+    let threshold = threshold.unwrap_or(0.5);
+    map_gray!(&img, |plane| to_binary_impl(plane, threshold))
+}
+
+fn to_binary_impl<T: Sample>(img: &Plane<T>, threshold: f32) -> Plane<T> {
+    let (width, height) = img.dimensions();
+    let mut binary_img = Plane::new(width, height);
+
+    for (x, y, pixel) in img.enumerate_pixels() {
+        let new_pixel = if (pixel.channels()[0].into_f64() / T::MAX) as f32 > threshold {
+            gray(T::DEFAULT_MAX_VALUE)
+        } else {
+            gray(T::DEFAULT_MIN_VALUE)
+        };
+        binary_img.put_pixel(x, y, new_pixel);
+    }
+
+    binary_img
+}
+
+pub fn invert(img: DynamicImage) -> DynamicImage {
+    map_buffer!(&img, |img| invert_impl(img))
+}
+
+fn invert_impl<P>(img: &ImageBuffer<P, Vec<P::Subpixel>>) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel + 'static,
+    P::Subpixel: Sample,
+{
+    let max = <P::Subpixel as Sample>::MAX;
+    let mut img = img.clone();
+    for pixel in img.pixels_mut() {
+        pixel.apply_without_alpha(|p| Sample::from_f64(max - p.into_f64()));
+    }
+    img
+}
+
+pub fn exponential(img: DynamicImage, exponent: Option<f32>) -> DynamicImage {
+    let exponent = exponent.unwrap_or(1.0);
+    map_buffer!(&img, |img| exponential_impl(img, exponent))
+}
+
+fn exponential_impl<P>(
+    img: &ImageBuffer<P, Vec<P::Subpixel>>,
+    exponent: f32,
+) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel + 'static,
+    P::Subpixel: Sample,
+{
+    let max = <P::Subpixel as Sample>::MAX;
+    let mut exp_img = img.clone();
+    for pixel in exp_img.pixels_mut() {
+        pixel.apply_without_alpha(|p| {
+            let normalized = (p.into_f64() / max) as f32;
+            Sample::from_f64((normalized.powf(exponent) * max as f32) as f64)
+        });
+    }
+    exp_img
+}
+
+pub fn hist_equalize(img: DynamicImage) -> DynamicImage {
+    // Histogram equalization is a bit more involved.
+    // Using the `histogram` crate to simplify this:
+    use imageproc::contrast::equalize_histogram;
+
+    match Depth::of(&img) {
+        Depth::U8 => DynamicImage::ImageLuma8(equalize_histogram(&img.to_luma8())),
+        Depth::U16 => DynamicImage::ImageLuma16(equalize_histogram_deep(&img.to_luma16())),
+        Depth::F32 => gray_to_rgb32f(equalize_histogram_deep(&img.to_luma32f())),
+    }
+}
+
+/// Histogram equalization on 65536 bins, for what `imageproc` can't take.
+fn equalize_histogram_deep<T: Sample>(img: &Plane<T>) -> Plane<T> {
+    const BINS: usize = 1 << 16;
+    let bin = |p: T| ((p.into_f64() / T::MAX).clamp(0.0, 1.0) * (BINS - 1) as f64).round() as usize;
+
+    let mut cumulative = vec![0u64; BINS];
+    for pixel in img.pixels() {
+        cumulative[bin(pixel.channels()[0])] += 1;
+    }
+    for i in 1..BINS {
+        cumulative[i] += cumulative[i - 1];
+    }
+    let total = cumulative[BINS - 1] as f64;
+
+    let (width, height) = img.dimensions();
+    Plane::from_fn(width, height, |x, y| {
+        let fraction = cumulative[bin(img.get_pixel(x, y).channels()[0])] as f64 / total;
+        gray(T::round_from_f64(fraction * T::MAX))
+    })
+}
+
+pub(crate) fn register(registry: &mut Registry) {
+    registry.add("color/invert", vec![], |img, _| Ok(invert(img.clone())));
+    registry.add(
+        "color/exponential",
+        vec![ParamSpec::float("exponent", 0.0, f64::INFINITY, Some(1.0))],
+        |img, args| {
+            Ok(exponential(
+                img.clone(),
+                Some(args.float("exponent") as f32),
+            ))
+        },
+    );
+    registry.add("color/hist_equalize", vec![], |img, _| {
+        Ok(hist_equalize(img.clone()))
+    });
+    registry.add("color/to_gray", vec![], |img, _| Ok(to_gray(img.clone())));
+    registry.add(
+        "color/to_binary",
+        vec![ParamSpec::float("threshold", 0.0, 1.0, Some(0.5))],
+        |img, args| Ok(to_binary(img.clone(), Some(args.float("threshold") as f32))),
+    );
+}
@@ -0,0 +1,1743 @@
+use image::{ColorType, DynamicImage, GenericImageView, Pixel};
+use rustfft::{num_complex::Complex, FftPlanner};
+use std::cmp::Ordering;
+use std::f64::consts::PI;
+
+use crate::error::DipError;
+use crate::transform::border::{border, border_param, BorderMode};
+use crate::transform::geometric::affine;
+use crate::transform::interpolation::{interpolation, interpolation_param, Interpolation};
+use crate::transform::registry::{Args, Measurements, Output, ParamSpec, Registry};
+use crate::transform::spectrum::{Spectrum, SpectrumView};
+use crate::transform::utils::{
+    channels_f64, from_channels_f64, gray, map_gray, par_for_each, Depth, Plane, Sample,
+};
+
+// The maths below was written for 8-bit samples, so other depths are brought
+// to the same 0..255 scale on the way in and out, without rounding.
+
+fn to_8bit_scale<T: Sample>(value: T) -> f64 {
+    value.into_f64() * (255.0 / T::MAX)
+}
+
+fn from_8bit_scale<T: Sample>(value: f64) -> T {
+    T::from_f64(value * (T::MAX / 255.0))
+}
+
+pub(crate) fn dft_2d_complex(
+    data: &[Vec<Complex<f64>>],
+    planner: &mut FftPlanner<f64>,
+    inverse: bool,
+) -> Vec<Vec<Complex<f64>>> {
+    let height = data.len();
+    let width = data[0].len();
+    let plan = |planner: &mut FftPlanner<f64>, len| {
+        if inverse {
+            planner.plan_fft_inverse(len)
+        } else {
+            planner.plan_fft_forward(len)
+        }
+    };
+
+    // Row-wise (I)DFT
+    let fft = plan(planner, width);
+    let mut transformed = data.to_vec();
+    par_for_each(&mut transformed, |_, row| {
+        fft.process(row);
+        if inverse {
+            for val in row.iter_mut() {
+                *val /= width as f64;
+            }
+        }
+    });
+
+    // Column-wise (I)DFT
+    let fft = plan(planner, height);
+    let mut columns: Vec<Vec<Complex<f64>>> = (0..width)
+        .map(|j| transformed.iter().map(|row| row[j]).collect())
+        .collect();
+    par_for_each(&mut columns, |_, col| {
+        fft.process(col);
+        if inverse {
+            for val in col.iter_mut() {
+                *val /= height as f64;
+            }
+        }
+    });
+    for (j, col) in columns.iter().enumerate() {
+        for (row, &val) in transformed.iter_mut().zip(col) {
+            row[j] = val;
+        }
+    }
+
+    transformed
+}
+
+/// A taper applied to an image before its DFT. Without one, the jump between
+/// opposite edges shows up in the spectrum as a bright cross through the
+/// centre.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Window {
+    Rectangular,
+    Hann,
+    /// Stops just short of 0 at the edges, for a narrower central peak.
+    Hamming,
+    /// Leaks less than Hann far from a peak, but blurs it more.
+    Blackman,
+    /// Flat, tapering like Hann over the outer `alpha / 2` at either end, so
+    /// 0 is no window and 1 is Hann.
+    Tukey(f64),
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Window::Rectangular
+    }
+}
+
+impl Window {
+    pub const NAMES: [&'static str; 5] = ["none", "hann", "hamming", "blackman", "tukey"];
+
+    /// `alpha` only matters for Tukey windows.
+    pub fn from_name(name: &str, alpha: f64) -> Option<Window> {
+        match name {
+            "none" => Some(Window::Rectangular),
+            "hann" => Some(Window::Hann),
+            "hamming" => Some(Window::Hamming),
+            "blackman" => Some(Window::Blackman),
+            "tukey" => Some(Window::Tukey(alpha)),
+            _ => None,
+        }
+    }
+
+    /// The weight of sample `i` of `len`.
+    pub fn weight(self, i: usize, len: usize) -> f64 {
+        if len < 2 {
+            return 1.0;
+        }
+        let t = i as f64 / (len - 1) as f64;
+        let cos = |cycles: f64| (2.0 * PI * cycles * t).cos();
+        match self {
+            Window::Rectangular => 1.0,
+            Window::Hann => 0.5 - 0.5 * cos(1.0),
+            Window::Hamming => 0.54 - 0.46 * cos(1.0),
+            Window::Blackman => (0.42 - 0.5 * cos(1.0) + 0.08 * cos(2.0)).max(0.0),
+            Window::Tukey(alpha) => {
+                let edge = t.min(1.0 - t);
+                if edge >= alpha / 2.0 {
+                    1.0
+                } else {
+                    0.5 - 0.5 * (2.0 * PI * edge / alpha).cos()
+                }
+            }
+        }
+    }
+}
+
+/// How far an image is padded with zeros before its DFT. Padding samples the
+/// spectrum more finely, and padding to twice the size makes products of
+/// spectra convolve linearly rather than wrapping around the edges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Padding {
+    None,
+    /// To the next power of two on either side.
+    PowerOfTwo,
+    /// To 2M × 2N.
+    Double,
+    /// To at least this width and height. Larger sides are kept.
+    Size(u32, u32),
+}
+
+impl Default for Padding {
+    fn default() -> Self {
+        Padding::None
+    }
+}
+
+/// Most samples an image may be padded to, 8192 × 8192 or about 1 GiB of
+/// complex samples per channel.
+pub const MAX_PADDED_SAMPLES: u64 = 1 << 26;
+
+impl Padding {
+    pub const NAMES: [&'static str; 4] = ["none", "power_of_two", "double", "size"];
+
+    /// `size` only matters for `"size"`.
+    pub fn from_name(name: &str, size: (u32, u32)) -> Option<Padding> {
+        match name {
+            "none" => Some(Padding::None),
+            "power_of_two" => Some(Padding::PowerOfTwo),
+            "double" => Some(Padding::Double),
+            "size" => Some(Padding::Size(size.0, size.1)),
+            _ => None,
+        }
+    }
+
+    /// The size an image of `width` × `height` is padded to, or an error if
+    /// that is more than [`MAX_PADDED_SAMPLES`].
+    pub fn padded(self, width: u32, height: u32) -> Result<(u32, u32), DipError> {
+        let (width, height) = (u64::from(width), u64::from(height));
+        let (padded_width, padded_height) = match self {
+            Padding::None => return Ok((width as u32, height as u32)),
+            Padding::PowerOfTwo => (width.next_power_of_two(), height.next_power_of_two()),
+            Padding::Double => (width * 2, height * 2),
+            Padding::Size(w, h) => (width.max(w.into()), height.max(h.into())),
+        };
+        check_padded((width, height), (padded_width, padded_height))?;
+        Ok((padded_width as u32, padded_height as u32))
+    }
+}
+
+/// Fails if padding an image of `size` to `padded` takes more than
+/// [`MAX_PADDED_SAMPLES`].
+fn check_padded(size: (u64, u64), padded: (u64, u64)) -> Result<(), DipError> {
+    if padded.0 * padded.1 > MAX_PADDED_SAMPLES {
+        return Err(DipError::InvalidArgument(format!(
+            "Padding {} x {} to {} x {} takes more than {} samples",
+            size.0, size.1, padded.0, padded.1, MAX_PADDED_SAMPLES
+        )));
+    }
+    Ok(())
+}
+
+/// How an image is prepared for its DFT: tapered by `window`, then padded
+/// with zeros to the right and below.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DftOptions {
+    pub window: Window,
+    pub padding: Padding,
+}
+
+/// `rows` tapered by `window` and padded with zeros to `size`.
+pub(crate) fn windowed(
+    rows: &[Vec<f64>],
+    window: Window,
+    (padded_width, padded_height): (usize, usize),
+) -> Vec<Vec<Complex<f64>>> {
+    let (width, height) = (rows[0].len(), rows.len());
+    let x_weights: Vec<f64> = (0..width).map(|x| window.weight(x, width)).collect();
+    let mut data = vec![vec![Complex::new(0.0, 0.0); padded_width]; padded_height];
+    for (y, (row, padded)) in rows.iter().zip(&mut data).enumerate() {
+        let y_weight = window.weight(y, height);
+        for ((padded, &value), x_weight) in padded.iter_mut().zip(row).zip(&x_weights) {
+            *padded = Complex::new(value * x_weight * y_weight, 0.0);
+        }
+    }
+    data
+}
+
+/// `size` is the padded size, checked by the caller.
+fn dft_2d_grayscale<T: Sample>(
+    gray_img: &Plane<T>,
+    inverse: bool,
+    with_log: bool,
+    (window, size): (Window, (u32, u32)),
+) -> Plane<T> {
+    let rows: Vec<Vec<f64>> = gray_img
+        .rows()
+        .map(|row| {
+            row.map(|pixel| to_8bit_scale(pixel.channels()[0]))
+                .collect()
+        })
+        .collect();
+    let data = windowed(&rows, window, (size.0 as usize, size.1 as usize));
+    let (width, height) = size;
+
+    let mut planner = FftPlanner::new();
+    let dft_result = dft_2d_complex(&data, &mut planner, inverse);
+
+    let mut k = 0.0f64;
+    let mut log_values: Vec<Vec<f64>> = vec![vec![0.0; width as usize]; height as usize];
+    for y in 0..height as usize {
+        for x in 0..width as usize {
+            let log_value = if with_log {
+                (1.0f64 + dft_result[y][x].norm()).ln()
+            } else {
+                dft_result[y][x].norm()
+            };
+            log_values[y][x] = log_value;
+            if log_value > k {
+                k = log_value;
+            }
+        }
+    }
+    let c = 255.0 / k;
+
+    // D(x, y) = c log(1 + |F(x, y)|)
+    let mut output_img = Plane::new(width, height);
+    for (y, row) in log_values.iter().enumerate() {
+        for (x, &log_value) in row.iter().enumerate() {
+            let pixel_value: T = from_8bit_scale(c * log_value);
+            output_img.put_pixel(x as u32, y as u32, gray(pixel_value));
+        }
+    }
+
+    output_img
+}
+
+fn shift_f64(data: &[Vec<f64>], shift_x: usize, shift_y: usize) -> Vec<Vec<f64>> {
+    let height = data.len();
+    let width = data[0].len();
+    let mut shifted_data = vec![vec![0.0f64; width]; height];
+
+    for (y, row) in data.iter().enumerate() {
+        for (x, &value) in row.iter().enumerate() {
+            let new_x = (x + shift_x) % width;
+            let new_y = (y + shift_y) % height;
+            shifted_data[new_y][new_x] = value;
+        }
+    }
+
+    shifted_data
+}
+
+pub(crate) fn shift_to_center_f64(data: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    shift_f64(data, data[0].len() / 2, data.len() / 2)
+}
+
+/// Undoes `shift_to_center_f64`, which is not its own inverse when a side is
+/// odd.
+fn shift_from_center_f64(data: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let (width, height) = (data[0].len(), data.len());
+    shift_f64(data, width - width / 2, height - height / 2)
+}
+
+/// `gain` of the offset (u, v) from the zero frequency for every frequency of
+/// a `width`×`height` spectrum, laid out like the unshifted spectrum.
+fn transfer_function(width: u32, height: u32, gain: impl Fn(f64, f64) -> f64) -> Vec<Vec<f64>> {
+    let mid_x = (width / 2) as f64;
+    let mid_y = (height / 2) as f64;
+    let centered: Vec<Vec<f64>> = (0..height)
+        .map(|y| {
+            (0..width)
+                .map(|x| gain(x as f64 - mid_x, y as f64 - mid_y))
+                .collect()
+        })
+        .collect();
+    shift_from_center_f64(&centered)
+}
+
+fn apply_dft_2d<T: Sample>(gray_img: &Plane<T>, prepared: (Window, (u32, u32))) -> Plane<T> {
+    dft_2d_grayscale(gray_img, false, true, prepared)
+}
+
+fn apply_dft_2d_no_log<T: Sample>(gray_img: &Plane<T>, prepared: (Window, (u32, u32))) -> Plane<T> {
+    dft_2d_grayscale(gray_img, false, false, prepared)
+}
+
+fn apply_idft_2d<T: Sample>(gray_img: &Plane<T>) -> Plane<T> {
+    let prepared = (Window::Rectangular, gray_img.dimensions());
+    dft_2d_grayscale(gray_img, true, true, prepared)
+}
+
+fn apply_dft_idft_2d<T: Sample>(gray_img: &Plane<T>) -> Plane<T> {
+    let (width, height) = gray_img.dimensions();
+
+    let mut data: Vec<Vec<Complex<f64>>> =
+        vec![vec![Complex::new(0.0, 0.0); width as usize]; height as usize];
+    for (x, y, pixel) in gray_img.enumerate_pixels() {
+        data[y as usize][x as usize] = Complex::new(to_8bit_scale(pixel.channels()[0]), 0.0);
+    }
+
+    let mut planner = FftPlanner::new();
+    let dft_result = dft_2d_complex(&data, &mut planner, false);
+    let idft_result = dft_2d_complex(&dft_result, &mut planner, true);
+
+    let mut output_img = Plane::new(width, height);
+    for (y, row) in idft_result.iter().enumerate() {
+        for (x, value) in row.iter().enumerate() {
+            let pixel_value: T = from_8bit_scale(value.re);
+            output_img.put_pixel(x as u32, y as u32, gray(pixel_value));
+        }
+    }
+
+    output_img
+}
+
+fn apply_shift_to_center<T: Sample>(img: &Plane<T>) -> Plane<T> {
+    let (width, height) = img.dimensions();
+    let mut shifted_img = Plane::new(width, height);
+
+    let half_width = width / 2;
+    let half_height = height / 2;
+
+    for y in 0..height {
+        for x in 0..width {
+            let new_x = (x + half_width) % width;
+            let new_y = (y + half_height) % height;
+            let pixel = img.get_pixel(x, y);
+            shifted_img.put_pixel(new_x, new_y, *pixel);
+        }
+    }
+
+    shifted_img
+}
+
+fn apply_homomorphic_filtering<T: Sample>(
+    gray_img: &Plane<T>,
+    r_l: f64,
+    r_h: f64,
+    c: f64,
+    d0: f64,
+) -> Plane<T> {
+    let (width, height) = gray_img.dimensions();
+
+    // 1. log
+    let mut data: Vec<Vec<Complex<f64>>> =
+        vec![vec![Complex::new(0.0, 0.0); width as usize]; height as usize];
+    for (x, y, pixel) in gray_img.enumerate_pixels() {
+        let log_val = (to_8bit_scale(pixel.channels()[0]) + 1.0).ln();
+        data[y as usize][x as usize] = Complex::new(log_val, 0.0);
+    }
+
+    // 2. FFT
+    let mut planner = FftPlanner::new();
+    let mut fft_data = dft_2d_complex(&data, &mut planner, false);
+
+    // 3. Filtering
+    // 3.1. Create Gaussian filter
+    let filter = transfer_function(width, height, |u, v| {
+        (r_h - r_l) * (1.0 - (-c * (u.hypot(v) / d0).powi(2)).exp()) + r_l
+    });
+    // 3.2. Apply the filter
+    for i in 0..height {
+        for j in 0..width {
+            fft_data[i as usize][j as usize] *= filter[i as usize][j as usize];
+        }
+    }
+
+    // 4. IFFT
+    let ifft_data = dft_2d_complex(&fft_data, &mut planner, true);
+
+    // 5. exp
+    let mut exp_data = vec![vec![0.0; width as usize]; height as usize];
+    for i in 0..height {
+        for j in 0..width {
+            exp_data[i as usize][j as usize] = ifft_data[i as usize][j as usize].exp().re - 1.0;
+        }
+    }
+
+    // 6. scale to [0, 255]
+    let min = exp_data
+        .iter()
+        .flatten()
+        .cloned()
+        .fold(f64::INFINITY, f64::min);
+    let max = exp_data
+        .iter()
+        .flatten()
+        .cloned()
+        .fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    let mut output_img = Plane::new(width, height);
+    for (y, row) in exp_data.iter().enumerate() {
+        for (x, &val) in row.iter().enumerate() {
+            let pixel_value = T::round_from_f64((val - min) / range * T::MAX);
+            output_img.put_pixel(x as u32, y as u32, gray(pixel_value));
+        }
+    }
+
+    output_img
+}
+
+/// The shape of a frequency-domain filter's transfer function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrequencyFilter {
+    /// Passes or stops frequencies outright, which rings around edges.
+    Ideal,
+    /// Rolls off more steeply the higher the order.
+    Butterworth(u32),
+    /// Rolls off smoothly and never rings.
+    Gaussian,
+}
+
+impl FrequencyFilter {
+    pub const NAMES: [&'static str; 3] = ["ideal", "butterworth", "gaussian"];
+
+    /// `order` only matters for Butterworth filters.
+    pub fn from_name(name: &str, order: u32) -> Option<FrequencyFilter> {
+        match name {
+            "ideal" => Some(FrequencyFilter::Ideal),
+            "butterworth" => Some(FrequencyFilter::Butterworth(order)),
+            "gaussian" => Some(FrequencyFilter::Gaussian),
+            _ => None,
+        }
+    }
+
+    /// Gain at `distance` from the zero frequency. `cutoff` is D0, and
+    /// `width` is W, the width of the band for band filters.
+    pub fn gain(self, band: Band, distance: f64, cutoff: f64, width: f64) -> f64 {
+        let (d, d0, w) = (distance, cutoff, width);
+        let low_pass = || match self {
+            FrequencyFilter::Ideal if d <= d0 => 1.0,
+            FrequencyFilter::Ideal => 0.0,
+            FrequencyFilter::Butterworth(n) => 1.0 / (1.0 + (d / d0).powi(2 * n as i32)),
+            FrequencyFilter::Gaussian => (-d * d / (2.0 * d0 * d0)).exp(),
+        };
+        let band_reject = || match self {
+            FrequencyFilter::Ideal if (d - d0).abs() <= w / 2.0 => 0.0,
+            FrequencyFilter::Ideal => 1.0,
+            // Gains of 0 at D = D0, where the ratio is infinite
+            FrequencyFilter::Butterworth(n) => {
+                1.0 / (1.0 + (d * w / (d * d - d0 * d0)).powi(2 * n as i32))
+            }
+            FrequencyFilter::Gaussian => 1.0 - (-((d * d - d0 * d0) / (d * w)).powi(2)).exp(),
+        };
+        match band {
+            Band::LowPass => low_pass(),
+            Band::HighPass => 1.0 - low_pass(),
+            Band::BandReject => band_reject(),
+            Band::BandPass => 1.0 - band_reject(),
+        }
+    }
+}
+
+/// Which frequencies a `FrequencyFilter` lets through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Band {
+    LowPass,
+    HighPass,
+    BandPass,
+    BandReject,
+}
+
+impl Band {
+    pub const NAMES: [&'static str; 4] = ["low_pass", "high_pass", "band_pass", "band_reject"];
+
+    pub fn from_name(name: &str) -> Option<Band> {
+        match name {
+            "low_pass" => Some(Band::LowPass),
+            "high_pass" => Some(Band::HighPass),
+            "band_pass" => Some(Band::BandPass),
+            "band_reject" => Some(Band::BandReject),
+            _ => None,
+        }
+    }
+}
+
+/// Gains of `filter` over a `width`×`height` spectrum, for
+/// `transfer_function`.
+pub(crate) fn filter_gains(
+    width: u32,
+    height: u32,
+    filter: FrequencyFilter,
+    band: Band,
+    cutoff: f64,
+    band_width: f64,
+) -> Vec<Vec<f64>> {
+    transfer_function(width, height, |u, v| {
+        filter.gain(band, u.hypot(v), cutoff, band_width)
+    })
+}
+
+/// Frequencies closer than this many notch radii to the zero frequency are
+/// never picked as noise, since that is where most of any image is.
+const NOTCH_CLEARANCE: f64 = 3.0;
+
+/// Offsets from the zero frequency of spikes in the magnitude spectrum of
+/// `rows`, samples on the 0..255 scale, one of each symmetric pair. A spike
+/// is a frequency whose log magnitude is more than `threshold` robust
+/// standard deviations above the median of the frequencies at the same
+/// distance from the centre, and the strongest within `radius` of it.
+pub(crate) fn detect_spikes(rows: &[Vec<f64>], radius: f64, threshold: f64) -> Vec<(f64, f64)> {
+    let (width, height) = (rows[0].len(), rows.len());
+    // Tapered, or the jump between opposite edges would show up as spikes
+    // along the axes
+    let data = windowed(rows, Window::Hann, (width, height));
+    let spectrum = dft_2d_complex(&data, &mut FftPlanner::new(), false);
+    let magnitude: Vec<Vec<f64>> = spectrum
+        .iter()
+        .map(|row| row.iter().map(|value| (1.0 + value.norm()).ln()).collect())
+        .collect();
+    let magnitude = shift_to_center_f64(&magnitude);
+    let (mid_x, mid_y) = ((width / 2) as f64, (height / 2) as f64);
+    let offset = |x: usize, y: usize| (x as f64 - mid_x, y as f64 - mid_y);
+
+    // Median and median absolute deviation of every ring of frequencies
+    let ring = |x: usize, y: usize| {
+        let (u, v) = offset(x, y);
+        u.hypot(v).round() as usize
+    };
+    let mut rings: Vec<Vec<f64>> = vec![];
+    for (y, row) in magnitude.iter().enumerate() {
+        for (x, &value) in row.iter().enumerate() {
+            let r = ring(x, y);
+            if rings.len() <= r {
+                rings.resize(r + 1, vec![]);
+            }
+            rings[r].push(value);
+        }
+    }
+    let median = |values: &mut Vec<f64>| {
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        values[values.len() / 2]
+    };
+    let stats: Vec<(f64, f64)> = rings
+        .into_iter()
+        .map(|mut values| {
+            if values.is_empty() {
+                return (0.0, 0.0);
+            }
+            let center = median(&mut values);
+            let mut deviations: Vec<f64> = values.iter().map(|v| (v - center).abs()).collect();
+            (center, 1.4826 * median(&mut deviations))
+        })
+        .collect();
+
+    let mut candidates = vec![];
+    for (y, row) in magnitude.iter().enumerate() {
+        for (x, &value) in row.iter().enumerate() {
+            let (u, v) = offset(x, y);
+            // The other half of the spectrum mirrors this one
+            if v > 0.0 || (v == 0.0 && u > 0.0) || u.hypot(v) < NOTCH_CLEARANCE * radius {
+                continue;
+            }
+            let (center, spread) = stats[ring(x, y)];
+            let score = (value - center) / spread.max(1e-6);
+            if score > threshold {
+                candidates.push((value, u, v));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+
+    let mut spikes: Vec<(f64, f64)> = vec![];
+    for (_, u, v) in candidates {
+        let taken = spikes
+            .iter()
+            .any(|&(su, sv)| (u - su).hypot(v - sv) <= radius || (u + su).hypot(v + sv) <= radius);
+        if !taken {
+            spikes.push((u, v));
+        }
+    }
+    spikes
+}
+
+/// Gains that stop discs of `radius` around each of `points` and around
+/// their mirror images through the zero frequency.
+pub(crate) fn notch_gains(
+    width: u32,
+    height: u32,
+    points: &[(f64, f64)],
+    radius: f64,
+    filter: FrequencyFilter,
+) -> Vec<Vec<f64>> {
+    let mut notches: Vec<(f64, f64)> = vec![];
+    for &(u, v) in points {
+        for point in [(u, v), (-u, -v)] {
+            if !notches.contains(&point) {
+                notches.push(point);
+            }
+        }
+    }
+    transfer_function(width, height, |u, v| {
+        notches
+            .iter()
+            .map(|&(nu, nv)| filter.gain(Band::HighPass, (u - nu).hypot(v - nv), radius, 0.0))
+            .product()
+    })
+}
+
+fn to_complex(rows: &[Vec<f64>]) -> Vec<Vec<Complex<f64>>> {
+    rows.iter()
+        .map(|row| row.iter().map(|&v| Complex::new(v, 0.0)).collect())
+        .collect()
+}
+
+/// The gray levels of `img` from 0 to 1, tapered towards the edges so that
+/// they don't stand out in the spectrum.
+fn windowed_gray(img: &DynamicImage) -> Vec<Vec<f64>> {
+    let gray = img.to_luma32f();
+    let (width, height) = (gray.width() as usize, gray.height() as usize);
+    gray.rows()
+        .enumerate()
+        .map(|(y, row)| {
+            row.enumerate()
+                .map(|(x, p)| {
+                    p.0[0] as f64 * Window::Hann.weight(x, width) * Window::Hann.weight(y, height)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The (dx, dy) by which `b` is shifted from `a`, so that b(x, y) is about
+/// a(x - dx, y - dy), refined to a fraction of a pixel. Also returns the
+/// height of the correlation peak, which is 1 for a perfect match.
+fn phase_correlate(
+    a: &[Vec<f64>],
+    b: &[Vec<f64>],
+    planner: &mut FftPlanner<f64>,
+) -> (f64, f64, f64) {
+    let fa = dft_2d_complex(&to_complex(a), planner, false);
+    let fb = dft_2d_complex(&to_complex(b), planner, false);
+    // The normalised cross-power spectrum, whose inverse is a single peak
+    let cross: Vec<Vec<Complex<f64>>> = fa
+        .iter()
+        .zip(&fb)
+        .map(|(row_a, row_b)| {
+            row_a
+                .iter()
+                .zip(row_b)
+                .map(|(va, vb)| {
+                    let product = vb * va.conj();
+                    let norm = product.norm();
+                    if norm > 1e-12 {
+                        product / norm
+                    } else {
+                        Complex::new(0.0, 0.0)
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let surface = dft_2d_complex(&cross, planner, true);
+
+    let (height, width) = (surface.len() as i64, surface[0].len() as i64);
+    let (mut peak_x, mut peak_y, mut peak) = (0, 0, f64::NEG_INFINITY);
+    for (y, row) in surface.iter().enumerate() {
+        for (x, value) in row.iter().enumerate() {
+            if value.re > peak {
+                (peak_x, peak_y, peak) = (x as i64, y as i64, value.re);
+            }
+        }
+    }
+
+    let at =
+        |x: i64, y: i64| surface[y.rem_euclid(height) as usize][x.rem_euclid(width) as usize].re;
+    // Vertex of the parabola through the peak and its neighbours
+    let refine = |before: f64, after: f64| {
+        let curvature = before - 2.0 * peak + after;
+        if curvature.abs() < 1e-12 {
+            0.0
+        } else {
+            (0.5 * (before - after) / curvature).clamp(-0.5, 0.5)
+        }
+    };
+    let unwrap = |i: i64, len: i64| if i > len / 2 { i - len } else { i };
+    let dx = unwrap(peak_x, width) as f64 + refine(at(peak_x - 1, peak_y), at(peak_x + 1, peak_y));
+    let dy = unwrap(peak_y, height) as f64 + refine(at(peak_x, peak_y - 1), at(peak_x, peak_y + 1));
+    (dx, dy, peak)
+}
+
+/// Rows of angles from 0 to 180° and columns of log-spaced radii in the
+/// log-polar resampling of the magnitude spectrum.
+const LOG_POLAR_SIZE: (usize, usize) = (360, 256);
+
+/// The magnitude spectrum of `rows` around its centre, with angles down the
+/// rows and the log of the radius, up to `max_radius`, across the columns.
+/// Rotating the image shifts it down and scaling it shifts it across.
+fn log_polar_spectrum(
+    rows: &[Vec<f64>],
+    max_radius: f64,
+    planner: &mut FftPlanner<f64>,
+) -> Vec<Vec<f64>> {
+    let spectrum = dft_2d_complex(&to_complex(rows), planner, false);
+    let (height, width) = (spectrum.len(), spectrum[0].len());
+    // Low frequencies are about the same in any two photos, so they are
+    // damped to let the rest decide
+    let magnitude: Vec<Vec<f64>> = spectrum
+        .iter()
+        .enumerate()
+        .map(|(y, row)| {
+            let fy = (y as f64 / height as f64).min(1.0 - y as f64 / height as f64);
+            row.iter()
+                .enumerate()
+                .map(|(x, value)| {
+                    let fx = (x as f64 / width as f64).min(1.0 - x as f64 / width as f64);
+                    let c = (PI * fx).cos() * (PI * fy).cos();
+                    (1.0 + value.norm()).ln() * (1.0 - c) * (2.0 - c)
+                })
+                .collect()
+        })
+        .collect();
+    let magnitude = shift_to_center_f64(&magnitude);
+    let (center_x, center_y) = ((width / 2) as f64, (height / 2) as f64);
+
+    let bilinear = |x: f64, y: f64| {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let value = |x: f64, y: f64| {
+            if x < 0.0 || y < 0.0 || x >= width as f64 || y >= height as f64 {
+                0.0
+            } else {
+                magnitude[y as usize][x as usize]
+            }
+        };
+        (1.0 - fy) * ((1.0 - fx) * value(x0, y0) + fx * value(x0 + 1.0, y0))
+            + fy * ((1.0 - fx) * value(x0, y0 + 1.0) + fx * value(x0 + 1.0, y0 + 1.0))
+    };
+    let (angles, radii) = LOG_POLAR_SIZE;
+    (0..angles)
+        .map(|i| {
+            let (sin, cos) = (PI * i as f64 / angles as f64).sin_cos();
+            (0..radii)
+                .map(|j| {
+                    let radius = max_radius.powf(j as f64 / radii as f64);
+                    bilinear(center_x + radius * cos, center_y + radius * sin)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The affine matrix that undoes scaling by `scale` and rotating clockwise by
+/// `angle` degrees about `center`, followed by a shift of `shift`.
+fn undo_similarity(
+    angle: f64,
+    scale: f64,
+    (center_x, center_y): (f64, f64),
+    (dx, dy): (f64, f64),
+) -> [[f64; 3]; 2] {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    // Inverse of scale × rotation
+    let a = [[cos / scale, sin / scale], [-sin / scale, cos / scale]];
+    let (x, y) = (center_x + dx, center_y + dy);
+    [
+        [a[0][0], a[0][1], center_x - a[0][0] * x - a[0][1] * y],
+        [a[1][0], a[1][1], center_y - a[1][0] * x - a[1][1] * y],
+    ]
+}
+
+/// Estimates how `img2` is shifted, and with `similarity` also rotated and
+/// scaled about its centre, from `img`, and moves it back into line with
+/// `img`. The measurements are `dx` and `dy` in pixels, `rotation` in
+/// degrees clockwise, `scale`, and `response`, which is near 1 when the
+/// images match well and near 0 when they have little in common.
+pub fn register_images(
+    img: &DynamicImage,
+    img2: &DynamicImage,
+    similarity: bool,
+    interpolation: Interpolation,
+    border: BorderMode,
+) -> Result<Output, DipError> {
+    if img.dimensions() != img2.dimensions() {
+        return Err(DipError::DimensionMismatch {
+            expected: img.dimensions(),
+            actual: img2.dimensions(),
+        });
+    }
+    let (width, height) = img.dimensions();
+    let center = ((width as f64 - 1.0) / 2.0, (height as f64 - 1.0) / 2.0);
+    let mut planner = FftPlanner::new();
+    let reference = windowed_gray(img);
+
+    let mut candidates = vec![(0.0, 1.0)];
+    if similarity {
+        let max_radius = (width.min(height) as f64 / 2.0).max(2.0);
+        let polar = log_polar_spectrum(&reference, max_radius, &mut planner);
+        let polar2 = log_polar_spectrum(&windowed_gray(img2), max_radius, &mut planner);
+        let (shift_radius, shift_angle, _) = phase_correlate(&polar, &polar2, &mut planner);
+        let (angles, radii) = LOG_POLAR_SIZE;
+        let angle = shift_angle * 180.0 / angles as f64;
+        // Enlarging the image shrinks its spectrum
+        let scale = (-shift_radius * max_radius.ln() / radii as f64).exp();
+        // The magnitude spectrum can't tell a turn of 180° from none
+        let opposite = if angle > 0.0 {
+            angle - 180.0
+        } else {
+            angle + 180.0
+        };
+        candidates = vec![(angle, scale), (opposite, scale)];
+    }
+
+    let mut best: Option<(f64, f64, f64, f64, f64)> = None;
+    for (angle, scale) in candidates {
+        let unturned = if similarity {
+            let matrix = undo_similarity(angle, scale, center, (0.0, 0.0));
+            affine(
+                img2.clone(),
+                matrix,
+                Interpolation::Bilinear,
+                BorderMode::Constant,
+            )?
+        } else {
+            img2.clone()
+        };
+        let (dx, dy, response) =
+            phase_correlate(&reference, &windowed_gray(&unturned), &mut planner);
+        if best.map_or(true, |best| response > best.4) {
+            // Shifting before the turn is the same as shifting by the turned
+            // and scaled offset after it
+            let (sin, cos) = angle.to_radians().sin_cos();
+            let shift_x = scale * (cos * dx - sin * dy);
+            let shift_y = scale * (sin * dx + cos * dy);
+            best = Some((angle, scale, shift_x, shift_y, response));
+        }
+    }
+
+    let (angle, scale, dx, dy, response) = best.unwrap();
+    let matrix = undo_similarity(angle, scale, center, (dx, dy));
+    let image = affine(img2.clone(), matrix, interpolation, border)?;
+    let measurements = Measurements::from([
+        ("dx", dx),
+        ("dy", dy),
+        ("rotation", angle),
+        ("scale", scale),
+        ("response", response),
+    ]);
+    Ok(Output {
+        image,
+        measurements,
+        spectrum: None,
+    })
+}
+
+/// The gray levels of `img` from 0 to 1.
+fn gray_f64(img: &DynamicImage) -> Vec<Vec<f64>> {
+    img.to_luma32f()
+        .rows()
+        .map(|row| row.map(|p| p.0[0] as f64).collect())
+        .collect()
+}
+
+/// `rows` placed at (`left`, `top`) on a `width` × `height` canvas, the rest of
+/// which is made up by `border`.
+fn extended(
+    rows: &[Vec<f64>],
+    (left, top): (usize, usize),
+    (width, height): (usize, usize),
+    border: BorderMode,
+) -> Vec<Vec<f64>> {
+    let size = (rows[0].len() as u32, rows.len() as u32);
+    (0..height)
+        .map(|y| {
+            (0..width)
+                .map(
+                    |x| match border.locate(x as i64 - left as i64, y as i64 - top as i64, size) {
+                        Some((x, y)) => rows[y as usize][x as usize],
+                        None => 0.0,
+                    },
+                )
+                .collect()
+        })
+        .collect()
+}
+
+/// The smallest length of at least `len` with no prime factors above 5,
+/// which FFTs are much faster at than at large primes.
+fn fast_len(len: usize) -> usize {
+    (len..)
+        .find(|&n| {
+            let mut n = n;
+            for p in [2, 3, 5] {
+                while n % p == 0 {
+                    n /= p;
+                }
+            }
+            n == 1
+        })
+        .unwrap()
+}
+
+/// The size `img` is padded to for its convolution or correlation with
+/// `kernel`, large enough not to wrap around.
+fn linear_size(img: &DynamicImage, kernel: &DynamicImage) -> Result<(usize, usize), DipError> {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let size = (
+        fast_len(width + kernel.width() as usize - 1),
+        fast_len(height + kernel.height() as usize - 1),
+    );
+    check_padded(
+        (width as u64, height as u64),
+        (size.0 as u64, size.1 as u64),
+    )?;
+    Ok(size)
+}
+
+fn zero_padded(rows: &[Vec<f64>], size: (usize, usize)) -> Vec<Vec<Complex<f64>>> {
+    windowed(rows, Window::Rectangular, size)
+}
+
+/// Sums of `rows` over every rectangle from the origin, with a row and a
+/// column of zeros in front.
+fn summed_area(rows: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let width = rows[0].len();
+    let mut table = vec![vec![0.0; width + 1]];
+    for row in rows {
+        let mut sum = 0.0;
+        let mut line = vec![0.0];
+        for (x, value) in row.iter().enumerate() {
+            sum += value;
+            line.push(table.last().unwrap()[x + 1] + sum);
+        }
+        table.push(line);
+    }
+    table
+}
+
+/// Convolves every channel of `img` with the gray levels of `kernel`, scaled
+/// to add up to 1, so that large kernels blur without brightening. Pixel
+/// (width / 2, height / 2) of the kernel is its centre, and pixels outside of
+/// `img` are made up by `border`. The spectra are padded far enough that the
+/// result doesn't wrap around the edges.
+pub fn convolve(
+    img: &DynamicImage,
+    kernel: &DynamicImage,
+    border: BorderMode,
+) -> Result<DynamicImage, DipError> {
+    let size = linear_size(img, kernel)?;
+    let kernel = gray_f64(kernel);
+    let total: f64 = kernel.iter().flatten().sum();
+    if total <= 0.0 {
+        return Err(DipError::InvalidArgument(
+            "The kernel must not be black".to_string(),
+        ));
+    }
+    let kernel: Vec<Vec<f64>> = kernel
+        .iter()
+        .map(|row| row.iter().map(|value| value / total).collect())
+        .collect();
+    let (kernel_width, kernel_height) = (kernel[0].len(), kernel.len());
+    // So that the centre of the kernel lands on the pixel
+    let offset = (
+        kernel_width - 1 - kernel_width / 2,
+        kernel_height - 1 - kernel_height / 2,
+    );
+
+    let mut planner = FftPlanner::new();
+    let kernel = dft_2d_complex(&zero_padded(&kernel, size), &mut planner, false);
+    let channels: Vec<Vec<Vec<f64>>> = channels_f64(img)
+        .iter()
+        .map(|rows| {
+            let rows = to_complex(&extended(rows, offset, size, border));
+            let mut spectrum = dft_2d_complex(&rows, &mut planner, false);
+            for (row, gains) in spectrum.iter_mut().zip(&kernel) {
+                for (value, gain) in row.iter_mut().zip(gains) {
+                    *value *= gain;
+                }
+            }
+            dft_2d_complex(&spectrum, &mut planner, true)[kernel_height - 1..]
+                .iter()
+                .map(|row| row[kernel_width - 1..].iter().map(|v| v.re).collect())
+                .collect()
+        })
+        .collect();
+    Ok(from_channels_f64(img.color(), &channels, img.dimensions()))
+}
+
+/// How well `template` matches the gray levels of `img` when centred on each
+/// pixel, by normalised cross-correlation, so that neither brightness nor
+/// contrast matters. Scores go from -1, the negative of the template, through
+/// 0, unrelated, to 1, a perfect match, and are shown from black to white.
+/// Pixels outside of `img` are made up by `border`.
+///
+/// The measurements are `x` and `y`, where the best match is centred, and its
+/// `score`.
+pub fn correlate(
+    img: &DynamicImage,
+    template: &DynamicImage,
+    border: BorderMode,
+) -> Result<Output, DipError> {
+    let size = linear_size(img, template)?;
+    let template = gray_f64(template);
+    let (template_width, template_height) = (template[0].len(), template.len());
+    let count = (template_width * template_height) as f64;
+    let mean = template.iter().flatten().sum::<f64>() / count;
+    let template: Vec<Vec<f64>> = template
+        .iter()
+        .map(|row| row.iter().map(|value| value - mean).collect())
+        .collect();
+    let energy: f64 = template.iter().flatten().map(|v| v * v).sum();
+    if energy < 1e-9 {
+        return Err(DipError::InvalidArgument(
+            "The template must not be a single flat colour".to_string(),
+        ));
+    }
+
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let offset = (template_width / 2, template_height / 2);
+    let rows = extended(&gray_f64(img), offset, size, border);
+
+    let mut planner = FftPlanner::new();
+    let mut spectrum = dft_2d_complex(&to_complex(&rows), &mut planner, false);
+    let template = dft_2d_complex(&zero_padded(&template, size), &mut planner, false);
+    for (row, template) in spectrum.iter_mut().zip(&template) {
+        for (value, template) in row.iter_mut().zip(template) {
+            *value *= template.conj();
+        }
+    }
+    let products = dft_2d_complex(&spectrum, &mut planner, true);
+
+    // The template has a mean of 0, so only the spread of the image under it
+    // is left to divide by
+    let sums = summed_area(&rows);
+    let squares: Vec<Vec<f64>> = rows
+        .iter()
+        .map(|row| row.iter().map(|v| v * v).collect())
+        .collect();
+    let squares = summed_area(&squares);
+    let under = |table: &[Vec<f64>], x: usize, y: usize| {
+        let (right, bottom) = (x + template_width, y + template_height);
+        table[bottom][right] - table[y][right] - table[bottom][x] + table[y][x]
+    };
+    let scores: Vec<Vec<f64>> = (0..height)
+        .map(|y| {
+            (0..width)
+                .map(|x| {
+                    let sum = under(&sums, x, y);
+                    let spread = under(&squares, x, y) - sum * sum / count;
+                    if spread < 1e-9 {
+                        0.0
+                    } else {
+                        (products[y][x].re / (energy * spread).sqrt()).clamp(-1.0, 1.0)
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    let (mut best, mut best_score) = ((0, 0), f64::NEG_INFINITY);
+    for (y, row) in scores.iter().enumerate() {
+        for (x, &score) in row.iter().enumerate() {
+            if score > best_score {
+                best = (x, y);
+                best_score = score;
+            }
+        }
+    }
+    let color = match Depth::of(img) {
+        Depth::U8 => ColorType::L8,
+        Depth::U16 => ColorType::L16,
+        Depth::F32 => ColorType::Rgb32F,
+    };
+    let shown: Vec<Vec<f64>> = scores
+        .iter()
+        .map(|row| row.iter().map(|score| (score + 1.0) / 2.0).collect())
+        .collect();
+    let channels = vec![shown; color.channel_count() as usize];
+    Ok(Output {
+        image: from_channels_f64(color, &channels, img.dimensions()),
+        measurements: Measurements::from([
+            ("x", best.0 as f64),
+            ("y", best.1 as f64),
+            ("score", best_score),
+        ]),
+        spectrum: None,
+    })
+}
+
+/// The window and padded size of a DFT of `img`.
+fn prepared(img: &DynamicImage, options: DftOptions) -> Result<(Window, (u32, u32)), DipError> {
+    Ok((
+        options.window,
+        options.padding.padded(img.width(), img.height())?,
+    ))
+}
+
+pub fn dft(img: &DynamicImage, options: DftOptions) -> Result<DynamicImage, DipError> {
+    let prepared = prepared(img, options)?;
+    Ok(map_gray!(img, |plane| apply_shift_to_center(
+        &apply_dft_2d(plane, prepared)
+    )))
+}
+
+pub fn dft_non_shifted(img: &DynamicImage, options: DftOptions) -> Result<DynamicImage, DipError> {
+    let prepared = prepared(img, options)?;
+    Ok(map_gray!(img, |plane| apply_dft_2d(plane, prepared)))
+}
+
+pub fn dft_non_shifted_no_log(
+    img: &DynamicImage,
+    options: DftOptions,
+) -> Result<DynamicImage, DipError> {
+    let prepared = prepared(img, options)?;
+    Ok(map_gray!(img, |plane| apply_dft_2d_no_log(plane, prepared)))
+}
+
+pub fn dft_no_log(img: &DynamicImage, options: DftOptions) -> Result<DynamicImage, DipError> {
+    let prepared = prepared(img, options)?;
+    Ok(map_gray!(img, |plane| apply_shift_to_center(
+        &apply_dft_2d_no_log(plane, prepared)
+    )))
+}
+
+pub fn idft(img: &DynamicImage) -> DynamicImage {
+    map_gray!(img, |plane| apply_shift_to_center(&apply_idft_2d(plane)))
+}
+
+pub fn idft_non_shifted(img: &DynamicImage) -> DynamicImage {
+    map_gray!(img, |plane| apply_idft_2d(plane))
+}
+
+pub fn shift_to_center(img: &DynamicImage) -> DynamicImage {
+    map_gray!(img, |plane| apply_shift_to_center(plane))
+}
+
+pub fn homomorphic(
+    img: &DynamicImage,
+    r_l: Option<f32>,
+    r_h: Option<f32>,
+    c: Option<f32>,
+    d0: Option<f32>,
+) -> DynamicImage {
+    let r_l = r_l.unwrap_or(0.3) as f64;
+    let r_h = r_h.unwrap_or(2.0) as f64;
+    let c = c.unwrap_or(2.0) as f64;
+    let d0 = d0.unwrap_or(10.0) as f64;
+    map_gray!(img, |plane| apply_homomorphic_filtering(
+        plane, r_l, r_h, c, d0
+    ))
+}
+
+/// Filters every channel of `img` in the frequency domain. Frequencies are
+/// measured in cycles per image, so `cutoff` and `width` mean the same on
+/// images of the same size. High-pass results swing both ways around 0, and
+/// the negative half is cut off.
+pub fn frequency_filter(
+    img: &DynamicImage,
+    filter: FrequencyFilter,
+    band: Band,
+    cutoff: f64,
+    width: f64,
+) -> Result<DynamicImage, DipError> {
+    Ok(
+        Spectrum::forward(img, DftOptions::default(), SpectrumView::default())?
+            .filter(filter, band, cutoff, width)
+            .inverse(),
+    )
+}
+
+/// Removes periodic noise by stopping small discs of frequencies around each
+/// of `points`, given as (u, v) offsets from the centre of the `fft/dft`
+/// spectrum, and around their mirror images. With `detect`, spikes in the
+/// spectrum that stand out by more than that many standard deviations from
+/// other frequencies as far from the centre are stopped too.
+pub fn notch(
+    img: &DynamicImage,
+    points: &[(f64, f64)],
+    detect: Option<f64>,
+    radius: f64,
+    filter: FrequencyFilter,
+) -> Result<DynamicImage, DipError> {
+    Ok(
+        Spectrum::forward(img, DftOptions::default(), SpectrumView::default())?
+            .notch(points, detect, radius, filter)
+            .inverse(),
+    )
+}
+
+pub fn dft_idft(img: &DynamicImage) -> DynamicImage {
+    map_gray!(img, |plane| apply_dft_idft_2d(plane))
+}
+
+/// The padding and window params of the forward DFTs.
+fn dft_params() -> Vec<ParamSpec> {
+    vec![
+        ParamSpec::choice("window", &Window::NAMES, Some("none")),
+        ParamSpec::float("alpha", 0.0, 1.0, Some(0.5)),
+        ParamSpec::choice("padding", &Padding::NAMES, Some("none")),
+        ParamSpec::int("pad_width", 0, 8192, Some(0)),
+        ParamSpec::int("pad_height", 0, 8192, Some(0)),
+    ]
+}
+
+fn dft_options(args: &Args) -> DftOptions {
+    let size = (args.int("pad_width") as u32, args.int("pad_height") as u32);
+    DftOptions {
+        window: Window::from_name(args.str("window"), args.float("alpha")).unwrap_or_default(),
+        padding: Padding::from_name(args.str("padding"), size).unwrap_or_default(),
+    }
+}
+
+fn spectrum_view(args: &Args) -> SpectrumView {
+    SpectrumView::from_name(args.str("view")).unwrap_or_default()
+}
+
+fn frequency_filter_arg(args: &Args) -> FrequencyFilter {
+    FrequencyFilter::from_name(args.str("filter"), args.int("order") as u32)
+        .unwrap_or(FrequencyFilter::Gaussian)
+}
+
+fn band_arg(args: &Args) -> Band {
+    Band::from_name(args.str("band")).unwrap_or(Band::LowPass)
+}
+
+fn notch_points(args: &Args) -> Result<Vec<(f64, f64)>, DipError> {
+    let points = args.matrix("points");
+    if points.iter().any(|row| row.len() != 2) {
+        return Err(DipError::InvalidArgument(
+            "Notch points must be rows of \"u v\"".to_string(),
+        ));
+    }
+    Ok(points.iter().map(|row| (row[0], row[1])).collect())
+}
+
+/// The detection threshold of `fft/notch` in automatic mode.
+fn notch_detect(args: &Args, points: &[(f64, f64)]) -> Result<Option<f64>, DipError> {
+    match args.str("mode") {
+        "auto" => Ok(Some(args.float("threshold"))),
+        _ if points.is_empty() => Err(DipError::InvalidArgument(
+            "Manual notch filtering needs at least one point".to_string(),
+        )),
+        _ => Ok(None),
+    }
+}
+
+pub(crate) fn register(registry: &mut Registry) {
+    registry.add("fft/dft", dft_params(), |img, args| {
+        dft(img, dft_options(args))
+    });
+    let mut spectrum_params = vec![ParamSpec::choice(
+        "view",
+        &SpectrumView::NAMES,
+        Some("log_magnitude"),
+    )];
+    spectrum_params.extend(dft_params());
+    registry.add_spectral(
+        "fft/spectrum",
+        spectrum_params,
+        |img, args| Ok(Spectrum::forward(img, dft_options(args), spectrum_view(args))?.into()),
+        |spectrum, args| {
+            // Only transformed again if it should be windowed or padded
+            let (options, view) = (dft_options(args), spectrum_view(args));
+            Ok(if options == DftOptions::default() {
+                spectrum.with_view(view)
+            } else {
+                Spectrum::forward(&spectrum.inverse(), options, view)?
+            }
+            .into())
+        },
+    );
+    registry.add("fft/dft_non_shifted_no_log", dft_params(), |img, args| {
+        dft_non_shifted_no_log(img, dft_options(args))
+    });
+    registry.add("fft/dft_non_shifted", dft_params(), |img, args| {
+        dft_non_shifted(img, dft_options(args))
+    });
+    registry.add("fft/dft_no_log", dft_params(), |img, args| {
+        dft_no_log(img, dft_options(args))
+    });
+    registry.add_spectral(
+        "fft/idft",
+        vec![],
+        |img, _| Ok(idft(img).into()),
+        |spectrum, _| Ok(spectrum.inverse().into()),
+    );
+    registry.add_spectral(
+        "fft/idft_non_shifted",
+        vec![],
+        |img, _| Ok(idft_non_shifted(img).into()),
+        |spectrum, _| Ok(spectrum.inverse().into()),
+    );
+    registry.add("fft/shift_to_center", vec![], |img, _| {
+        Ok(shift_to_center(img))
+    });
+    registry.add(
+        "fft/homomorphic",
+        vec![
+            ParamSpec::float("r_l", 0.0, f64::INFINITY, Some(0.3)),
+            ParamSpec::float("r_h", 0.0, f64::INFINITY, Some(2.0)),
+            ParamSpec::float("c", 0.0, f64::INFINITY, Some(2.0)),
+            ParamSpec::float("d0", 0.01, f64::INFINITY, Some(10.0)),
+        ],
+        |img, args| {
+            Ok(homomorphic(
+                img,
+                Some(args.float("r_l") as f32),
+                Some(args.float("r_h") as f32),
+                Some(args.float("c") as f32),
+                Some(args.float("d0") as f32),
+            ))
+        },
+    );
+    registry.add("fft/dft_idft", vec![], |img, _| Ok(dft_idft(img)));
+    registry.add_spectral(
+        "fft/filter",
+        vec![
+            ParamSpec::choice("filter", &FrequencyFilter::NAMES, Some("gaussian")),
+            ParamSpec::choice("band", &Band::NAMES, Some("low_pass")),
+            ParamSpec::float("cutoff", 0.01, f64::INFINITY, Some(30.0)),
+            ParamSpec::float("width", 0.01, f64::INFINITY, Some(10.0)),
+            ParamSpec::int("order", 1, 20, Some(2)),
+        ],
+        |img, args| {
+            let (cutoff, width) = (args.float("cutoff"), args.float("width"));
+            let filter = frequency_filter_arg(args);
+            Ok(frequency_filter(img, filter, band_arg(args), cutoff, width)?.into())
+        },
+        |spectrum, args| {
+            let (cutoff, width) = (args.float("cutoff"), args.float("width"));
+            let filter = frequency_filter_arg(args);
+            Ok(spectrum
+                .filter(filter, band_arg(args), cutoff, width)
+                .into())
+        },
+    );
+    registry.add_spectral(
+        "fft/notch",
+        vec![
+            ParamSpec::choice("mode", &["auto", "manual"], Some("auto")),
+            ParamSpec::matrix("points", Some(vec![])),
+            ParamSpec::float("radius", 0.5, f64::INFINITY, Some(3.0)),
+            ParamSpec::choice("filter", &FrequencyFilter::NAMES, Some("gaussian")),
+            ParamSpec::int("order", 1, 20, Some(2)),
+            ParamSpec::float("threshold", 0.0, f64::INFINITY, Some(6.0)),
+        ],
+        |img, args| {
+            let points = notch_points(args)?;
+            let detect = notch_detect(args, &points)?;
+            let filter = frequency_filter_arg(args);
+            Ok(notch(img, &points, detect, args.float("radius"), filter)?.into())
+        },
+        |spectrum, args| {
+            let points = notch_points(args)?;
+            let detect = notch_detect(args, &points)?;
+            let filter = frequency_filter_arg(args);
+            Ok(spectrum
+                .notch(&points, detect, args.float("radius"), filter)
+                .into())
+        },
+    );
+    registry.add_binary("fft/convolve", vec![border_param()], |img, img2, args| {
+        convolve(img, img2, border(args))
+    });
+    registry.add_binary_measured("fft/correlate", vec![border_param()], |img, img2, args| {
+        correlate(img, img2, border(args))
+    });
+    registry.add_binary_measured(
+        "fft/register",
+        vec![
+            ParamSpec::choice("mode", &["translation", "similarity"], Some("translation")),
+            interpolation_param(),
+            border_param(),
+        ],
+        |img, img2, args| {
+            register_images(
+                img,
+                img2,
+                args.str("mode") == "similarity",
+                interpolation(args),
+                border(args),
+            )
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma, Rgb, RgbImage};
+
+    /// Blobs of different sizes, so that no shift or turn looks like another.
+    fn blobs(size: u32) -> DynamicImage {
+        let blobs = [
+            (40.0, 30.0, 6.0),
+            (90.0, 45.0, 10.0),
+            (60.0, 85.0, 4.0),
+            (30.0, 95.0, 8.0),
+            (75.0, 60.0, 3.0),
+        ];
+        DynamicImage::ImageLuma8(GrayImage::from_fn(size, size, |x, y| {
+            let value: f64 = blobs
+                .iter()
+                .map(|&(bx, by, radius)| {
+                    let d2 = (x as f64 - bx).powi(2) + (y as f64 - by).powi(2);
+                    (-d2 / (2.0 * radius * radius)).exp()
+                })
+                .sum();
+            Luma([(value.min(1.0) * 230.0 + 10.0) as u8])
+        }))
+    }
+
+    /// Moves pixels by rotating clockwise by `angle` and scaling by `scale`
+    /// about the centre, then shifting by (dx, dy).
+    fn similarity(img: &DynamicImage, angle: f64, scale: f64, dx: f64, dy: f64) -> DynamicImage {
+        let center = (img.width() as f64 - 1.0) / 2.0;
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let (a, b, c, d) = (scale * cos, -scale * sin, scale * sin, scale * cos);
+        let matrix = [
+            [a, b, center + dx - a * center - b * center],
+            [c, d, center + dy - c * center - d * center],
+        ];
+        affine(
+            img.clone(),
+            matrix,
+            Interpolation::Bilinear,
+            BorderMode::Constant,
+        )
+        .unwrap()
+    }
+
+    fn measure(img2: &DynamicImage, similarity: bool) -> Measurements {
+        let output = register_images(
+            &blobs(128),
+            img2,
+            similarity,
+            Interpolation::Bilinear,
+            BorderMode::Constant,
+        )
+        .unwrap();
+        output.measurements
+    }
+
+    #[test]
+    fn phase_correlation_finds_sub_pixel_shifts() {
+        let m = measure(&similarity(&blobs(128), 0.0, 1.0, 7.5, -4.25), false);
+        assert!((m["dx"] - 7.5).abs() < 0.3, "{:?}", m);
+        assert!((m["dy"] + 4.25).abs() < 0.3, "{:?}", m);
+        assert!(m["response"] > 0.3, "{:?}", m);
+    }
+
+    #[test]
+    fn log_polar_correlation_finds_rotation_and_scale() {
+        let m = measure(&similarity(&blobs(128), 12.0, 1.1, 3.0, -2.0), true);
+        assert!((m["rotation"] - 12.0).abs() < 1.0, "{:?}", m);
+        assert!((m["scale"] - 1.1).abs() < 0.03, "{:?}", m);
+        assert!((m["dx"] - 3.0).abs() < 1.0, "{:?}", m);
+        assert!((m["dy"] + 2.0).abs() < 1.0, "{:?}", m);
+    }
+
+    #[test]
+    fn complementary_bands_add_up_to_one() {
+        let filters = [
+            FrequencyFilter::Ideal,
+            FrequencyFilter::Butterworth(2),
+            FrequencyFilter::Gaussian,
+        ];
+        for filter in filters {
+            for d in [0.0, 5.0, 19.0, 20.0, 26.0, 80.0] {
+                let gain = |band| filter.gain(band, d, 20.0, 8.0);
+                let sum = gain(Band::LowPass) + gain(Band::HighPass);
+                assert!((sum - 1.0).abs() < 1e-12, "{:?} at {}", filter, d);
+                let sum = gain(Band::BandPass) + gain(Band::BandReject);
+                assert!((sum - 1.0).abs() < 1e-12, "{:?} at {}", filter, d);
+            }
+        }
+        let half = FrequencyFilter::Butterworth(3).gain(Band::LowPass, 20.0, 20.0, 8.0);
+        assert!((half - 0.5).abs() < 1e-12);
+        let stopped = FrequencyFilter::Gaussian.gain(Band::BandReject, 20.0, 20.0, 8.0);
+        assert!(stopped.abs() < 1e-12);
+    }
+
+    #[test]
+    fn transfer_functions_are_centred_on_the_zero_frequency() {
+        for (width, height) in [(8, 6), (7, 5)] {
+            let gains = transfer_function(width, height, f64::hypot);
+            assert_eq!(gains[0][0], 0.0);
+            assert_eq!(gains[0][1], 1.0);
+            assert_eq!(gains[0][width as usize - 1], 1.0);
+            assert_eq!(gains[1][0], 1.0);
+            assert_eq!(gains[height as usize - 1][0], 1.0);
+        }
+    }
+
+    #[test]
+    fn low_pass_filters_smooth_away_fine_detail() {
+        let checkerboard = DynamicImage::ImageLuma8(GrayImage::from_fn(32, 32, |x, y| {
+            Luma([if (x + y) % 2 == 0 { 200 } else { 100 }])
+        }));
+        for filter in ["ideal", "butterworth", "gaussian"] {
+            let filter = FrequencyFilter::from_name(filter, 2).unwrap();
+            // Butterworth filters let a little through past the cutoff
+            let near = |value: u8, expected: i32| (value as i32 - expected).abs() <= 1;
+            let low = frequency_filter(&checkerboard, filter, Band::LowPass, 8.0, 4.0).unwrap();
+            assert!(
+                low.to_luma8().pixels().all(|p| near(p.0[0], 150)),
+                "{:?}",
+                filter
+            );
+            let high = frequency_filter(&checkerboard, filter, Band::HighPass, 8.0, 4.0).unwrap();
+            assert!(
+                near(high.to_luma8().get_pixel(0, 0).0[0], 50),
+                "{:?}",
+                filter
+            );
+            assert_eq!(high.to_luma8().get_pixel(1, 0).0[0], 0, "{:?}", filter);
+        }
+    }
+
+    /// Blobs with a ripple of (u, v) cycles per image added on top.
+    fn rippled(u: f64, v: f64) -> Plane<u8> {
+        let blobs = blobs(128).to_luma8();
+        GrayImage::from_fn(128, 128, |x, y| {
+            let phase = 2.0 * PI * (u * x as f64 + v * y as f64) / 128.0;
+            let value = 40.0 + blobs.get_pixel(x, y).0[0] as f64 * 0.6 + 25.0 * phase.sin();
+            Luma([value.round() as u8])
+        })
+    }
+
+    fn mean_difference(a: &Plane<u8>, b: &Plane<u8>) -> f64 {
+        let total: f64 = a
+            .pixels()
+            .zip(b.pixels())
+            .map(|(a, b)| (a.0[0] as f64 - b.0[0] as f64).abs())
+            .sum();
+        total / a.len() as f64
+    }
+
+    #[test]
+    fn spikes_from_periodic_noise_are_detected() {
+        let rows = |img: Plane<u8>| -> Vec<Vec<f64>> {
+            img.rows()
+                .map(|row| row.map(|p| p.0[0] as f64).collect())
+                .collect()
+        };
+        assert_eq!(
+            detect_spikes(&rows(rippled(16.0, 8.0)), 3.0, 6.0),
+            [(-16.0, -8.0)]
+        );
+        assert_eq!(detect_spikes(&rows(rippled(0.0, 0.0)), 3.0, 6.0), []);
+        // A NaN sample makes every magnitude NaN, and none of them stands out
+        let mut samples = rows(rippled(16.0, 8.0));
+        samples[3][5] = f64::NAN;
+        assert_eq!(detect_spikes(&samples, 3.0, 6.0), []);
+    }
+
+    #[test]
+    fn notches_remove_periodic_noise() {
+        let clean = rippled(0.0, 0.0);
+        let noisy = rippled(16.0, 8.0);
+        let noisy_image = DynamicImage::ImageLuma8(noisy.clone());
+        assert!(mean_difference(&noisy, &clean) > 10.0);
+
+        // Either of the pair stops both
+        for points in [[(16.0, 8.0)], [(-16.0, -8.0)]] {
+            let manual =
+                notch(&noisy_image, &points, None, 3.0, FrequencyFilter::Gaussian).unwrap();
+            let difference = mean_difference(&manual.to_luma8(), &clean);
+            assert!(difference < 2.0, "{:?}: {}", points, difference);
+        }
+        let auto = notch(&noisy_image, &[], Some(6.0), 3.0, FrequencyFilter::Ideal).unwrap();
+        assert!(mean_difference(&auto.to_luma8(), &clean) < 2.0);
+    }
+
+    #[test]
+    fn registering_images_of_different_sizes_fails() {
+        let output = register_images(
+            &blobs(128),
+            &blobs(64),
+            false,
+            Interpolation::Bilinear,
+            BorderMode::Constant,
+        );
+        assert!(matches!(output, Err(DipError::DimensionMismatch { .. })));
+    }
+
+    #[test]
+    fn windows_are_symmetric_and_taper_to_the_edges() {
+        let windows = [
+            Window::Hann,
+            Window::Hamming,
+            Window::Blackman,
+            Window::Tukey(0.5),
+        ];
+        for window in windows {
+            let weights: Vec<f64> = (0..9).map(|i| window.weight(i, 9)).collect();
+            assert!((weights[4] - 1.0).abs() < 1e-12, "{:?}", window);
+            for i in 0..4 {
+                assert!((weights[i] - weights[8 - i]).abs() < 1e-12, "{:?}", window);
+                assert!(weights[i] <= weights[i + 1], "{:?}", window);
+            }
+        }
+        assert!((Window::Hamming.weight(0, 9) - 0.08).abs() < 1e-12);
+        assert_eq!(Window::Tukey(0.5).weight(3, 9), 1.0);
+
+        for i in 0..9 {
+            assert_eq!(Window::Tukey(0.0).weight(i, 9), 1.0);
+            let hann = Window::Hann.weight(i, 9);
+            assert!((Window::Tukey(1.0).weight(i, 9) - hann).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn padding_only_ever_enlarges() {
+        assert_eq!(Padding::None.padded(100, 37).unwrap(), (100, 37));
+        assert_eq!(Padding::PowerOfTwo.padded(100, 64).unwrap(), (128, 64));
+        assert_eq!(Padding::Double.padded(100, 37).unwrap(), (200, 74));
+        assert_eq!(Padding::Size(50, 64).padded(100, 37).unwrap(), (100, 64));
+    }
+
+    #[test]
+    fn padding_too_large_is_refused() {
+        for padding in [Padding::Size(65536, 65536), Padding::Double] {
+            assert!(matches!(
+                padding.padded(20000, 15000),
+                Err(DipError::InvalidArgument(_))
+            ));
+        }
+        assert_eq!(Padding::None.padded(20000, 15000).unwrap(), (20000, 15000));
+        assert!(Padding::Size(8192, 8192).padded(100, 100).is_ok());
+    }
+
+    #[test]
+    fn windows_remove_the_cross_from_the_edges() {
+        // The right edge is bright, the left edge dark
+        let img =
+            DynamicImage::ImageLuma8(GrayImage::from_fn(64, 48, |x, y| Luma([(x * 3 + y) as u8])));
+        let leakage = |window| {
+            let options = DftOptions {
+                window,
+                padding: Padding::None,
+            };
+            dft(&img, options).unwrap().to_luma8().get_pixel(4, 24).0[0]
+        };
+        let (plain, windowed) = (leakage(Window::Rectangular), leakage(Window::Hann));
+        assert!(windowed + 50 < plain, "{} {}", windowed, plain);
+    }
+
+    #[test]
+    fn padded_dfts_are_larger() {
+        let img = DynamicImage::ImageLuma8(GrayImage::new(20, 12));
+        let options = DftOptions {
+            window: Window::Blackman,
+            padding: Padding::PowerOfTwo,
+        };
+        assert_eq!(dft(&img, options).unwrap().dimensions(), (32, 16));
+    }
+
+    #[test]
+    fn a_kernel_of_one_pixel_moves_the_image() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_fn(9, 7, |x, y| {
+            Rgb([(x * 25) as u8, (y * 30) as u8, (x * y) as u8])
+        }));
+        let dot = DynamicImage::ImageLuma8(GrayImage::from_pixel(1, 1, Luma([255])));
+        assert_eq!(convolve(&img, &dot, BorderMode::Wrap).unwrap(), img);
+
+        let kernel = DynamicImage::ImageLuma8(GrayImage::from_fn(3, 3, |x, y| {
+            Luma([if (x, y) == (2, 1) { 80 } else { 0 }])
+        }));
+        let moved = convolve(&img, &kernel, BorderMode::Wrap).unwrap();
+        let rgb = img.to_rgb8();
+        let expected = RgbImage::from_fn(9, 7, |x, y| *rgb.get_pixel((x + 8) % 9, y));
+        assert_eq!(moved, DynamicImage::ImageRgb8(expected));
+
+        let black = DynamicImage::ImageLuma8(GrayImage::new(3, 3));
+        assert!(convolve(&img, &black, BorderMode::Wrap).is_err());
+    }
+
+    #[test]
+    fn oversized_kernels_are_refused() {
+        // 9000 x 9000 once padded, more than MAX_PADDED_SAMPLES
+        let img = DynamicImage::ImageLuma8(GrayImage::from_pixel(9000, 1, Luma([10])));
+        let kernel = DynamicImage::ImageLuma8(GrayImage::from_fn(1, 9000, |_, y| {
+            Luma([(y % 7 * 30) as u8])
+        }));
+        assert!(matches!(
+            convolve(&img, &kernel, BorderMode::Wrap),
+            Err(DipError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            correlate(&img, &kernel, BorderMode::Wrap),
+            Err(DipError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn correlation_finds_the_template() {
+        let img = blobs(128);
+        // Dimmer, so only a normalised correlation still matches it
+        let template = DynamicImage::ImageLuma8(GrayImage::from_fn(24, 20, |x, y| {
+            Luma([img.get_pixel(x + 78, y + 35).0[0] / 2 + 20])
+        }));
+        let output = correlate(&img, &template, BorderMode::Reflect101).unwrap();
+        let m = output.measurements;
+        assert_eq!((m["x"], m["y"]), (90.0, 45.0), "{:?}", m);
+        assert!(m["score"] > 0.99, "{:?}", m);
+        assert_eq!(output.image.color(), ColorType::L8);
+        assert_eq!(output.image.to_luma8().get_pixel(90, 45), &Luma([255]));
+
+        let flat = DynamicImage::ImageLuma8(GrayImage::from_pixel(5, 5, Luma([90])));
+        assert!(correlate(&img, &flat, BorderMode::Reflect101).is_err());
+    }
+}
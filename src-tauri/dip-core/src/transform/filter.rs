@@ -0,0 +1,1154 @@
+use image::{DynamicImage, ImageBuffer, Pixel};
+use std::cmp::Ordering;
+
+use crate::error::DipError;
+use crate::transform::border::{border, border_param, BorderMode};
+use crate::transform::registry::{Args, ParamSpec, Registry};
+use crate::transform::utils::{
+    apply_kernel_f64_gray, apply_kernel_i32_gray, get_pixel_grayscale, gray, lab_to_rgb,
+    map_buffer, merge_rgb, par_bands, par_rows, rgb, rgb_to_lab, rgb_to_ycbcr, split_rgb,
+    ycbcr_to_rgb, Plane, RgbBuffer, Sample,
+};
+
+/// Which channels of a colour image a filter runs on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channels {
+    /// R, G and B, each on its own.
+    Rgb,
+    /// Only Y of YCbCr, leaving the chroma untouched.
+    YCbCr,
+    /// Only L* of CIELAB.
+    Lab,
+}
+
+/// A filter that runs on one channel at a time, at any bit depth.
+enum SpatialFilter {
+    Mean(u32),
+    /// Window size and rank, from 0 for the minimum to 1 for the maximum.
+    Rank(u32, f64),
+    /// One dimension of a separable kernel.
+    Gaussian(Vec<f64>),
+    Kernel(Vec<Vec<i32>>),
+    /// Adds the response of the kernel to the image, like `binary_op` "add".
+    Sharpen(Vec<Vec<i32>>),
+    /// A kernel as written by the user, with its anchor (column, row) and a
+    /// bias relative to white.
+    Custom {
+        kernel: Vec<Vec<f64>>,
+        anchor: (usize, usize),
+        offset: f64,
+    },
+}
+
+impl SpatialFilter {
+    fn apply<T: Sample>(&self, plane: &Plane<T>, border: BorderMode) -> Plane<T> {
+        match self {
+            SpatialFilter::Mean(kernel_size) => mean_filter(plane, *kernel_size, border),
+            SpatialFilter::Rank(kernel_size, rank) => {
+                rank_filter(plane, *kernel_size, *rank, border)
+            }
+            SpatialFilter::Gaussian(kernel) => gaussian_filter(plane, kernel, border),
+            SpatialFilter::Kernel(kernel) => apply_kernel_i32_gray(plane, kernel, border),
+            SpatialFilter::Sharpen(kernel) => {
+                let mut result = apply_kernel_i32_gray(plane, kernel, border);
+                for (pixel, original) in result.pixels_mut().zip(plane.pixels()) {
+                    let sum = pixel.channels()[0].into_f64() + original.channels()[0].into_f64();
+                    pixel.channels_mut()[0] = T::from_f64(sum);
+                }
+                result
+            }
+            SpatialFilter::Custom {
+                kernel,
+                anchor,
+                offset,
+            } => apply_kernel_f64_gray(plane, kernel, *anchor, offset * T::MAX, border),
+        }
+    }
+
+    /// Runs the filter on the channels of `img` selected by `channels`. Alpha
+    /// is passed through, so that edge detectors don't make the image
+    /// transparent, and the result has the colour type of `img`.
+    fn run(&self, img: &DynamicImage, channels: Channels, border: BorderMode) -> DynamicImage {
+        map_buffer!(img, |buf| self.map_pixels(buf, channels, border))
+    }
+
+    fn map_pixels<P>(
+        &self,
+        img: &ImageBuffer<P, Vec<P::Subpixel>>,
+        channels: Channels,
+        border: BorderMode,
+    ) -> ImageBuffer<P, Vec<P::Subpixel>>
+    where
+        P: Pixel + 'static,
+        P::Subpixel: Sample,
+    {
+        let (width, height) = img.dimensions();
+        let mut result = img.clone();
+        if P::CHANNEL_COUNT < 3 {
+            let luma = Plane::from_fn(width, height, |x, y| {
+                gray(img.get_pixel(x, y).channels()[0])
+            });
+            let luma = self.apply(&luma, border);
+            for (pixel, l) in result.pixels_mut().zip(luma.pixels()) {
+                pixel.channels_mut()[0] = l.channels()[0];
+            }
+        } else {
+            let colour = RgbBuffer::from_fn(width, height, |x, y| {
+                *<P::Subpixel as Sample>::RgbPixel::from_slice(&img.get_pixel(x, y).channels()[..3])
+            });
+            let colour = self.map_rgb(&colour, channels, border);
+            for (pixel, c) in result.pixels_mut().zip(colour.pixels()) {
+                pixel.channels_mut()[..3].copy_from_slice(c.channels());
+            }
+        }
+        result
+    }
+
+    fn map_rgb<T: Sample>(
+        &self,
+        img: &RgbBuffer<T>,
+        channels: Channels,
+        border: BorderMode,
+    ) -> RgbBuffer<T> {
+        match channels {
+            Channels::Rgb => merge_rgb(&split_rgb(img).map(|plane| self.apply(&plane, border))),
+            Channels::YCbCr => self.map_luminance(img, rgb_to_ycbcr, ycbcr_to_rgb, border),
+            Channels::Lab => self.map_luminance(img, rgb_to_lab, lab_to_rgb, border),
+        }
+    }
+
+    /// Filters the first component of the luminance/chrominance colour space
+    /// given by `forward` and `inverse`.
+    fn map_luminance<T: Sample>(
+        &self,
+        img: &RgbBuffer<T>,
+        forward: fn([f32; 3]) -> [f32; 3],
+        inverse: fn([f32; 3]) -> [f32; 3],
+        border: BorderMode,
+    ) -> RgbBuffer<T> {
+        let (width, height) = img.dimensions();
+        let converted: Vec<[f32; 3]> = img
+            .pixels()
+            .map(|p| {
+                let c = p.channels();
+                forward([0, 1, 2].map(|i| (c[i].into_f64() / T::MAX) as f32))
+            })
+            .collect();
+        let luma = Plane::from_fn(width, height, |x, y| {
+            let l = converted[(y * width + x) as usize][0];
+            gray(T::round_from_f64(l as f64 * T::MAX))
+        });
+        let luma = self.apply(&luma, border);
+
+        let mut result = RgbBuffer::new(width, height);
+        for ((pixel, c), l) in result.pixels_mut().zip(&converted).zip(luma.pixels()) {
+            let l = (l.channels()[0].into_f64() / T::MAX) as f32;
+            let values = inverse([l, c[1], c[2]]);
+            *pixel = rgb(values.map(|v| T::round_from_f64(v as f64 * T::MAX)));
+        }
+        result
+    }
+}
+
+/// Mean of the `kernel_size`² window around each pixel, from running sums
+/// along rows and then columns, so the cost doesn't grow with the window.
+fn mean_filter<T: Sample>(img: &Plane<T>, kernel_size: u32, border: BorderMode) -> Plane<T> {
+    let dimensions = img.dimensions();
+    let radius = kernel_size as i64 / 2;
+    let count = ((2 * radius + 1) * (2 * radius + 1)) as f64;
+
+    let sums = box_sum_pass(&plane_values(img), dimensions, radius, border, false);
+    let sums = box_sum_pass(&sums, dimensions, radius, border, true);
+    values_to_plane(&sums, dimensions, |sum| sum / count)
+}
+
+/// The value at `rank` (0 for the minimum, 1 for the maximum) of the sorted
+/// `kernel_size`² window around each pixel.
+fn rank_filter<T: Sample>(
+    img: &Plane<T>,
+    kernel_size: u32,
+    rank: f64,
+    border: BorderMode,
+) -> Plane<T> {
+    let r = kernel_size as i32 / 2;
+    let window_len = ((2 * r + 1) * (2 * r + 1)) as usize;
+    let k = (rank.clamp(0.0, 1.0) * (window_len - 1) as f64).round() as usize;
+    match T::LEVELS {
+        Some(levels) => histogram_rank_filter(img, r, k, levels, border),
+        None => sorting_rank_filter(img, r, k, border),
+    }
+}
+
+/// Selects the `k`th smallest value of every window.
+fn sorting_rank_filter<T: Sample>(
+    img: &Plane<T>,
+    r: i32,
+    k: usize,
+    border: BorderMode,
+) -> Plane<T> {
+    let (width, height) = img.dimensions();
+    let mut result = Plane::new(width, height);
+
+    par_rows(&mut result, width as usize, |y, row| {
+        let mut window = vec![];
+        for (x, result) in row.iter_mut().enumerate() {
+            window.clear();
+            for dx in -r..=r {
+                for dy in -r..=r {
+                    let px = x as i32 + dx;
+                    let py = y as i32 + dy;
+                    window.push(get_pixel_grayscale(img, px, py, border));
+                }
+            }
+            let (_, value, _) = window
+                .select_nth_unstable_by(k, |a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+            *result = *value;
+        }
+    });
+    result
+}
+
+/// Huang's algorithm: slides a histogram of the window along each row,
+/// updating it by one column per pixel, and follows the `k`th smallest
+/// value from the previous pixel instead of searching from scratch.
+fn histogram_rank_filter<T: Sample>(
+    img: &Plane<T>,
+    r: i32,
+    k: usize,
+    levels: usize,
+    border: BorderMode,
+) -> Plane<T> {
+    let (width, height) = img.dimensions();
+    let mut result = Plane::new(width, height);
+
+    par_bands(&mut result, width as usize, |first_row, band| {
+        let mut histogram = vec![0usize; levels];
+        for (i, row) in band.chunks_mut(width as usize).enumerate() {
+            let y = (first_row + i) as i32;
+            let column = |x: i32| {
+                (-r..=r).map(move |dy| get_pixel_grayscale(img, x, y + dy, border).level())
+            };
+            histogram.iter_mut().for_each(|count| *count = 0);
+            for x in -r..=r {
+                for level in column(x) {
+                    histogram[level] += 1;
+                }
+            }
+            // `below` counts the values in the window that are less than `level`
+            let (mut level, mut below) = (0, 0);
+
+            for (x, result) in row.iter_mut().enumerate() {
+                let x = x as i32;
+                if x > 0 {
+                    for old in column(x - r - 1) {
+                        histogram[old] -= 1;
+                        if old < level {
+                            below -= 1;
+                        }
+                    }
+                    for new in column(x + r) {
+                        histogram[new] += 1;
+                        if new < level {
+                            below += 1;
+                        }
+                    }
+                }
+                while below > k {
+                    level -= 1;
+                    below -= histogram[level];
+                }
+                while below + histogram[level] <= k {
+                    below += histogram[level];
+                    level += 1;
+                }
+                *result = T::from_f64(level as f64);
+            }
+        }
+    });
+    result
+}
+
+/// Radius of a Gaussian kernel that covers ±3 sigma.
+fn gaussian_radius(sigma: f32) -> usize {
+    (3.0 * sigma).ceil().max(1.0) as usize
+}
+
+/// One dimension of a normalised Gaussian kernel. The 2D kernel is the
+/// outer product of this with itself.
+fn generate_gaussian_kernel(size: usize, sigma: f32) -> Vec<f64> {
+    let k = (size - 1) / 2;
+    let sigma = sigma as f64;
+    let mut kernel: Vec<f64> = (0..size)
+        .map(|i| {
+            let x = i as f64 - k as f64;
+            (-x * x / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+
+    // Normalize
+    let sum: f64 = kernel.iter().sum();
+    for value in kernel.iter_mut() {
+        *value /= sum;
+    }
+
+    kernel
+}
+
+/// Gaussian blur as a horizontal and then a vertical pass of `kernel`.
+fn gaussian_filter<T: Sample>(img: &Plane<T>, kernel: &[f64], border: BorderMode) -> Plane<T> {
+    let dimensions = img.dimensions();
+    // Where `generate_gaussian_kernel` puts the peak, also for even sizes
+    let anchor = (kernel.len() - 1) / 2;
+
+    let values = correlate_pass(
+        &plane_values(img),
+        dimensions,
+        kernel,
+        anchor,
+        border,
+        false,
+    );
+    let values = correlate_pass(&values, dimensions, kernel, anchor, border, true);
+    values_to_plane(&values, dimensions, |value| value)
+}
+
+fn plane_values<T: Sample>(img: &Plane<T>) -> Vec<f64> {
+    img.pixels().map(|p| p.channels()[0].into_f64()).collect()
+}
+
+fn values_to_plane<T: Sample>(
+    values: &[f64],
+    (width, height): (u32, u32),
+    f: impl Fn(f64) -> f64,
+) -> Plane<T> {
+    Plane::from_fn(width, height, |x, y| {
+        gray(T::from_f64(f(values[(y * width + x) as usize])))
+    })
+}
+
+/// Correlates every row (or column, if `vertical`) of `values` with the 1D
+/// `kernel`, whose element at `anchor` lies over the output sample.
+fn correlate_pass(
+    values: &[f64],
+    (width, height): (u32, u32),
+    kernel: &[f64],
+    anchor: usize,
+    border: BorderMode,
+    vertical: bool,
+) -> Vec<f64> {
+    let width = width as usize;
+    let mut result = vec![0.0; values.len()];
+
+    par_rows(&mut result, width, |y, row| {
+        for (k, weight) in kernel.iter().enumerate() {
+            let offset = k as i64 - anchor as i64;
+            if vertical {
+                if let Some(j) = border.index(y as i64 + offset, height) {
+                    let source = &values[j as usize * width..][..width];
+                    for (sum, value) in row.iter_mut().zip(source) {
+                        *sum += value * weight;
+                    }
+                }
+            } else {
+                let source = &values[y * width..][..width];
+                for (x, sum) in row.iter_mut().enumerate() {
+                    if let Some(j) = border.index(x as i64 + offset, width as u32) {
+                        *sum += source[j as usize] * weight;
+                    }
+                }
+            }
+        }
+    });
+    result
+}
+
+/// Sums the `2 * radius + 1` samples around each one along every row (or
+/// column, if `vertical`), sliding the window instead of re-summing it.
+fn box_sum_pass(
+    values: &[f64],
+    (width, height): (u32, u32),
+    radius: i64,
+    border: BorderMode,
+    vertical: bool,
+) -> Vec<f64> {
+    if vertical {
+        // Along the rows of the transpose, so the window slides down whole
+        // columns and every sample costs the same whatever the radius
+        let sums = box_sum_pass(
+            &transpose(values, width as usize),
+            (height, width),
+            radius,
+            border,
+            false,
+        );
+        return transpose(&sums, height as usize);
+    }
+
+    let width = width as usize;
+    let mut result = vec![0.0; values.len()];
+    par_rows(&mut result, width, |y, row| {
+        let source = &values[y * width..][..width];
+        let value = |x: i64| {
+            border
+                .index(x, width as u32)
+                .map_or(0.0, |x| source[x as usize])
+        };
+        let mut sum: f64 = (-radius..=radius).map(value).sum();
+        for (x, result) in row.iter_mut().enumerate() {
+            *result = sum;
+            sum += value(x as i64 + radius + 1) - value(x as i64 - radius);
+        }
+    });
+    result
+}
+
+/// `values`, rows of `width` samples, with rows and columns swapped.
+fn transpose(values: &[f64], width: usize) -> Vec<f64> {
+    let height = values.len().checked_div(width).unwrap_or(0);
+    (0..width)
+        .flat_map(|x| (0..height).map(move |y| values[y * width + x]))
+        .collect()
+}
+
+fn sobel_kernel(direction: &str) -> Vec<Vec<i32>> {
+    match direction {
+        "h" => vec![vec![-1, 0, 1], vec![-2, 0, 2], vec![-1, 0, 1]],
+        "v" => vec![vec![-1, -2, -1], vec![0, 0, 0], vec![1, 2, 1]],
+        _ => vec![vec![0; 3]; 3], // Default to a null operation if no direction is specified
+    }
+}
+
+fn laplacian_kernel(neighbors: u8) -> Vec<Vec<i32>> {
+    match neighbors {
+        4 => vec![vec![0, 1, 0], vec![1, -4, 1], vec![0, 1, 0]],
+        8 => vec![vec![1, 1, 1], vec![1, -8, 1], vec![1, 1, 1]],
+        _ => vec![vec![0; 3]; 3], // Default to 4 neighbors if an invalid value is given
+    }
+}
+
+fn prewitt_kernel(direction: &str) -> Vec<Vec<i32>> {
+    match direction {
+        "h" => vec![vec![-1, 0, 1], vec![-1, 0, 1], vec![-1, 0, 1]],
+        "v" => vec![vec![-1, -1, -1], vec![0, 0, 0], vec![1, 1, 1]],
+        _ => vec![vec![0; 3]; 3], // Default to a null operation if no direction is specified
+    }
+}
+
+fn roberts_kernel(direction: &str) -> Vec<Vec<i32>> {
+    match direction {
+        "\\" => vec![vec![-1, 0], vec![0, 1]],
+        "/" => vec![vec![0, -1], vec![1, 0]],
+        _ => vec![vec![0; 2]; 2], // Default to a null operation if no direction is specified
+    }
+}
+
+pub fn mean(
+    img: &DynamicImage,
+    kernel_size: u32,
+    channels: Channels,
+    border: BorderMode,
+) -> DynamicImage {
+    SpatialFilter::Mean(kernel_size).run(img, channels, border)
+}
+
+pub fn median(
+    img: &DynamicImage,
+    kernel_size: u32,
+    channels: Channels,
+    border: BorderMode,
+) -> DynamicImage {
+    SpatialFilter::Rank(kernel_size, 0.5).run(img, channels, border)
+}
+
+/// Rank-order filter: the given `percentile` of each window, so 0 is the
+/// minimum, 50 the median and 100 the maximum.
+pub fn rank(
+    img: &DynamicImage,
+    kernel_size: u32,
+    percentile: f64,
+    channels: Channels,
+    border: BorderMode,
+) -> DynamicImage {
+    SpatialFilter::Rank(kernel_size, percentile / 100.0).run(img, channels, border)
+}
+
+/// Gaussian blur. Without a `kernel_size`, the kernel covers ±3 `sigma`.
+pub fn gaussian(
+    img: &DynamicImage,
+    kernel_size: Option<usize>,
+    sigma: f32,
+    channels: Channels,
+    border: BorderMode,
+) -> DynamicImage {
+    let kernel_size = kernel_size.unwrap_or(2 * gaussian_radius(sigma) + 1);
+    let kernel = generate_gaussian_kernel(kernel_size, sigma);
+    SpatialFilter::Gaussian(kernel).run(img, channels, border)
+}
+
+pub fn sobel(
+    img: &DynamicImage,
+    direction: &str,
+    channels: Channels,
+    border: BorderMode,
+) -> DynamicImage {
+    SpatialFilter::Kernel(sobel_kernel(direction)).run(img, channels, border)
+}
+
+pub fn laplacian(
+    img: &DynamicImage,
+    neighbors: u8,
+    channels: Channels,
+    border: BorderMode,
+) -> DynamicImage {
+    SpatialFilter::Kernel(laplacian_kernel(neighbors)).run(img, channels, border)
+}
+
+pub fn prewitt(
+    img: &DynamicImage,
+    direction: &str,
+    channels: Channels,
+    border: BorderMode,
+) -> DynamicImage {
+    SpatialFilter::Kernel(prewitt_kernel(direction)).run(img, channels, border)
+}
+
+pub fn roberts(
+    img: &DynamicImage,
+    direction: &str,
+    channels: Channels,
+    border: BorderMode,
+) -> DynamicImage {
+    SpatialFilter::Kernel(roberts_kernel(direction)).run(img, channels, border)
+}
+
+pub fn sobel_sharpen(
+    img: &DynamicImage,
+    direction: &str,
+    channels: Channels,
+    border: BorderMode,
+) -> DynamicImage {
+    SpatialFilter::Sharpen(sobel_kernel(direction)).run(img, channels, border)
+}
+
+pub fn laplacian_sharpen(
+    img: &DynamicImage,
+    neighbors: u8,
+    channels: Channels,
+    border: BorderMode,
+) -> DynamicImage {
+    SpatialFilter::Sharpen(laplacian_kernel(neighbors)).run(img, channels, border)
+}
+
+pub fn prewitt_sharpen(
+    img: &DynamicImage,
+    direction: &str,
+    channels: Channels,
+    border: BorderMode,
+) -> DynamicImage {
+    SpatialFilter::Sharpen(prewitt_kernel(direction)).run(img, channels, border)
+}
+
+pub fn roberts_sharpen(
+    img: &DynamicImage,
+    direction: &str,
+    channels: Channels,
+    border: BorderMode,
+) -> DynamicImage {
+    SpatialFilter::Sharpen(roberts_kernel(direction)).run(img, channels, border)
+}
+
+/// Correlates `img` with a user-defined `kernel` of any size, given as rows.
+///
+/// Each sum is divided by `divisor`, which defaults to the sum of the kernel
+/// (or 1 if that is 0), and then `offset` is added, as a fraction of white.
+/// `anchor` is the (column, row) of the kernel element over the output
+/// pixel and defaults to the centre.
+pub fn custom(
+    img: &DynamicImage,
+    kernel: &[Vec<f64>],
+    divisor: Option<f64>,
+    offset: f64,
+    anchor: Option<(usize, usize)>,
+    channels: Channels,
+    border: BorderMode,
+) -> Result<DynamicImage, DipError> {
+    let (columns, rows) = (kernel.first().map_or(0, |row| row.len()), kernel.len());
+    if columns == 0 || kernel.iter().any(|row| row.len() != columns) {
+        return Err(DipError::InvalidArgument(
+            "The kernel must be a non-empty matrix with rows of equal length".to_string(),
+        ));
+    }
+    let anchor = anchor.unwrap_or((columns / 2, rows / 2));
+    if anchor.0 >= columns || anchor.1 >= rows {
+        return Err(DipError::InvalidArgument(format!(
+            "Anchor ({}, {}) is outside of the {}x{} kernel",
+            anchor.0, anchor.1, columns, rows
+        )));
+    }
+    let divisor = divisor.unwrap_or_else(|| {
+        let sum: f64 = kernel.iter().flatten().sum();
+        if sum == 0.0 {
+            1.0
+        } else {
+            sum
+        }
+    });
+    if divisor == 0.0 {
+        return Err(DipError::InvalidArgument(
+            "The divisor must not be 0".to_string(),
+        ));
+    }
+    let kernel = kernel
+        .iter()
+        .map(|row| row.iter().map(|weight| weight / divisor).collect())
+        .collect();
+    let filter = SpatialFilter::Custom {
+        kernel,
+        anchor,
+        offset,
+    };
+    Ok(filter.run(img, channels, border))
+}
+
+fn kernel_size_param() -> ParamSpec {
+    ParamSpec::int("kernel_size", 1, 99, Some(3))
+}
+
+fn hv_direction_param() -> ParamSpec {
+    ParamSpec::choice("direction", &["h", "v"], None)
+}
+
+fn diagonal_direction_param() -> ParamSpec {
+    ParamSpec::choice("direction", &["\\", "/"], None)
+}
+
+fn neighbors_param() -> ParamSpec {
+    ParamSpec::choice("neighbors", &["4", "8"], Some("8"))
+}
+
+fn channels_param() -> ParamSpec {
+    ParamSpec::choice("channels", &["rgb", "ycbcr", "lab"], Some("rgb"))
+}
+
+fn channels(args: &Args) -> Channels {
+    match args.str("channels") {
+        "ycbcr" => Channels::YCbCr,
+        "lab" => Channels::Lab,
+        _ => Channels::Rgb,
+    }
+}
+
+fn neighbors(args: &Args) -> u8 {
+    match args.str("neighbors") {
+        "4" => 4,
+        _ => 8,
+    }
+}
+
+pub(crate) fn register(registry: &mut Registry) {
+    registry.add(
+        "filter/mean",
+        vec![kernel_size_param(), channels_param(), border_param()],
+        |img, args| {
+            Ok(mean(
+                img,
+                args.int("kernel_size") as u32,
+                channels(args),
+                border(args),
+            ))
+        },
+    );
+    registry.add(
+        "filter/median",
+        vec![kernel_size_param(), channels_param(), border_param()],
+        |img, args| {
+            Ok(median(
+                img,
+                args.int("kernel_size") as u32,
+                channels(args),
+                border(args),
+            ))
+        },
+    );
+    registry.add(
+        "filter/rank",
+        vec![
+            kernel_size_param(),
+            ParamSpec::float("percentile", 0.0, 100.0, Some(50.0)),
+            channels_param(),
+            border_param(),
+        ],
+        |img, args| {
+            Ok(rank(
+                img,
+                args.int("kernel_size") as u32,
+                args.float("percentile"),
+                channels(args),
+                border(args),
+            ))
+        },
+    );
+    registry.add(
+        "filter/gaussian",
+        vec![
+            // 0 sizes the kernel from sigma
+            ParamSpec::int("kernel_size", 0, 99, Some(0)),
+            ParamSpec::float("sigma", 0.01, 1000.0, Some(1.0)),
+            channels_param(),
+            border_param(),
+        ],
+        |img, args| {
+            Ok(gaussian(
+                img,
+                Some(args.int("kernel_size") as usize).filter(|&size| size > 0),
+                args.float("sigma") as f32,
+                channels(args),
+                border(args),
+            ))
+        },
+    );
+    registry.add(
+        "filter/sobel",
+        vec![hv_direction_param(), channels_param(), border_param()],
+        |img, args| {
+            Ok(sobel(
+                img,
+                args.str("direction"),
+                channels(args),
+                border(args),
+            ))
+        },
+    );
+    registry.add(
+        "filter/laplacian",
+        vec![neighbors_param(), channels_param(), border_param()],
+        |img, args| {
+            Ok(laplacian(
+                img,
+                neighbors(args),
+                channels(args),
+                border(args),
+            ))
+        },
+    );
+    registry.add(
+        "filter/prewitt",
+        vec![hv_direction_param(), channels_param(), border_param()],
+        |img, args| {
+            Ok(prewitt(
+                img,
+                args.str("direction"),
+                channels(args),
+                border(args),
+            ))
+        },
+    );
+    registry.add(
+        "filter/roberts",
+        vec![diagonal_direction_param(), channels_param(), border_param()],
+        |img, args| {
+            Ok(roberts(
+                img,
+                args.str("direction"),
+                channels(args),
+                border(args),
+            ))
+        },
+    );
+    registry.add(
+        "filter/sobel_sharpen",
+        vec![hv_direction_param(), channels_param(), border_param()],
+        |img, args| {
+            Ok(sobel_sharpen(
+                img,
+                args.str("direction"),
+                channels(args),
+                border(args),
+            ))
+        },
+    );
+    registry.add(
+        "filter/laplacian_sharpen",
+        vec![neighbors_param(), channels_param(), border_param()],
+        |img, args| {
+            Ok(laplacian_sharpen(
+                img,
+                neighbors(args),
+                channels(args),
+                border(args),
+            ))
+        },
+    );
+    registry.add(
+        "filter/prewitt_sharpen",
+        vec![hv_direction_param(), channels_param(), border_param()],
+        |img, args| {
+            Ok(prewitt_sharpen(
+                img,
+                args.str("direction"),
+                channels(args),
+                border(args),
+            ))
+        },
+    );
+    registry.add(
+        "filter/roberts_sharpen",
+        vec![diagonal_direction_param(), channels_param(), border_param()],
+        |img, args| {
+            Ok(roberts_sharpen(
+                img,
+                args.str("direction"),
+                channels(args),
+                border(args),
+            ))
+        },
+    );
+    registry.add(
+        "filter/custom",
+        vec![
+            ParamSpec::matrix("kernel", None),
+            // 0 divides by the sum of the kernel
+            ParamSpec::float("divisor", f64::NEG_INFINITY, f64::INFINITY, Some(0.0)),
+            ParamSpec::float("offset", -1.0, 1.0, Some(0.0)),
+            // -1 is the centre
+            ParamSpec::int("anchor_x", -1, 65535, Some(-1)),
+            ParamSpec::int("anchor_y", -1, 65535, Some(-1)),
+            channels_param(),
+            border_param(),
+        ],
+        |img, args| {
+            let kernel = args.matrix("kernel");
+            let divisor = Some(args.float("divisor")).filter(|&d| d != 0.0);
+            let anchor = match (args.int("anchor_x"), args.int("anchor_y")) {
+                (-1, -1) => None,
+                (-1, y) => Some((kernel[0].len() / 2, y as usize)),
+                (x, -1) => Some((x as usize, kernel.len() / 2)),
+                (x, y) => Some((x as usize, y as usize)),
+            };
+            custom(
+                img,
+                kernel,
+                divisor,
+                args.float("offset"),
+                anchor,
+                channels(args),
+                border(args),
+            )
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma, Rgb, RgbImage, Rgba, RgbaImage};
+
+    fn colour_image() -> RgbImage {
+        RgbImage::from_fn(9, 7, |x, y| Rgb([(x * 28) as u8, (y * 36) as u8, 200]))
+    }
+
+    #[test]
+    fn filters_keep_the_colour_type() {
+        let rgb = DynamicImage::ImageRgb8(colour_image());
+        for channels in [Channels::Rgb, Channels::YCbCr, Channels::Lab] {
+            assert!(matches!(
+                mean(&rgb, 3, channels, BorderMode::default()),
+                DynamicImage::ImageRgb8(_)
+            ));
+        }
+        let gray = DynamicImage::ImageLuma8(rgb.to_luma8());
+        assert!(matches!(
+            sobel(&gray, "h", Channels::Rgb, BorderMode::default()),
+            DynamicImage::ImageLuma8(_)
+        ));
+        let deep = DynamicImage::ImageRgb16(rgb.to_rgb16());
+        assert!(matches!(
+            median(&deep, 3, Channels::Lab, BorderMode::default()),
+            DynamicImage::ImageRgb16(_)
+        ));
+        let float = DynamicImage::ImageRgba32F(rgb.to_rgba32f());
+        assert!(matches!(
+            gaussian(&float, Some(3), 1.0, Channels::YCbCr, BorderMode::default()),
+            DynamicImage::ImageRgba32F(_)
+        ));
+    }
+
+    #[test]
+    fn rgb_channels_are_filtered_independently() {
+        let img = colour_image();
+        let result = mean(
+            &DynamicImage::ImageRgb8(img.clone()),
+            3,
+            Channels::Rgb,
+            BorderMode::default(),
+        )
+        .to_rgb8();
+        let planes: [GrayImage; 3] = split_rgb(&img);
+        for (c, plane) in planes.iter().enumerate() {
+            let expected = mean_filter(plane, 3, BorderMode::default());
+            for (x, y, pixel) in result.enumerate_pixels() {
+                assert_eq!(pixel.0[c], expected.get_pixel(x, y).0[0]);
+            }
+        }
+    }
+
+    #[test]
+    fn deeper_images_filter_like_8_bit_ones() {
+        let img = DynamicImage::ImageRgb8(colour_image());
+        let shallow = gaussian(&img, Some(5), 1.5, Channels::Rgb, BorderMode::default());
+        let deep = gaussian(
+            &DynamicImage::ImageRgb16(img.to_rgb16()),
+            Some(5),
+            1.5,
+            Channels::Rgb,
+            BorderMode::default(),
+        );
+        for (a, b) in shallow.to_rgb8().pixels().zip(deep.to_rgb8().pixels()) {
+            for c in 0..3 {
+                assert!((a.0[c] as i32 - b.0[c] as i32).abs() <= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn alpha_is_passed_through() {
+        let img = RgbaImage::from_fn(5, 5, |x, y| Rgba([100, 150, 200, (x * 50 + y) as u8]));
+        let result = laplacian(
+            &DynamicImage::ImageRgba8(img.clone()),
+            8,
+            Channels::Rgb,
+            BorderMode::default(),
+        );
+        let result = match result {
+            DynamicImage::ImageRgba8(result) => result,
+            other => panic!("unexpected colour type {:?}", other.color()),
+        };
+        for (a, b) in result.pixels().zip(img.pixels()) {
+            assert_eq!(a.0[3], b.0[3]);
+        }
+    }
+
+    #[test]
+    fn luminance_filters_leave_a_flat_image_unchanged() {
+        let img = RgbImage::from_pixel(6, 6, Rgb([180, 40, 90]));
+        for channels in [Channels::YCbCr, Channels::Lab] {
+            let result = median(
+                &DynamicImage::ImageRgb8(img.clone()),
+                3,
+                channels,
+                BorderMode::default(),
+            )
+            .to_rgb8();
+            for (a, b) in result.pixels().zip(img.pixels()) {
+                for c in 0..3 {
+                    assert!((a.0[c] as i32 - b.0[c] as i32).abs() <= 2, "{:?}", channels);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn only_constant_borders_darken_the_edges() {
+        let img = GrayImage::from_pixel(5, 4, Luma([200]));
+        for name in BorderMode::NAMES {
+            let border = BorderMode::from_name(name).unwrap();
+            let result = mean_filter(&img, 3, border);
+            let corner = result.get_pixel(0, 0).0[0];
+            if border == BorderMode::Constant {
+                assert_eq!(corner, (200.0 * 4.0 / 9.0) as u8);
+            } else {
+                assert_eq!(result, img, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn custom_kernels_are_written_as_rows() {
+        let img = DynamicImage::ImageRgb8(colour_image());
+        let border = BorderMode::Replicate;
+        let rows = vec![
+            vec![-1.0, -2.0, -1.0],
+            vec![0.0, 0.0, 0.0],
+            vec![1.0, 2.0, 1.0],
+        ];
+        let result = custom(&img, &rows, Some(1.0), 0.0, None, Channels::Rgb, border).unwrap();
+        assert_eq!(result, sobel(&img, "h", Channels::Rgb, border));
+
+        // Anchored on the left, the output at x is the input at x + 1
+        let shift = vec![vec![0.0, 1.0]];
+        let result = custom(&img, &shift, None, 0.0, Some((0, 0)), Channels::Rgb, border)
+            .unwrap()
+            .to_rgb8();
+        let img = img.to_rgb8();
+        assert_eq!(result.get_pixel(3, 2), img.get_pixel(4, 2));
+        assert_eq!(result.get_pixel(8, 2), img.get_pixel(8, 2));
+    }
+
+    #[test]
+    fn custom_kernels_apply_divisor_and_offset() {
+        let img = DynamicImage::ImageLuma8(GrayImage::from_pixel(4, 4, Luma([100])));
+        let kernel = vec![vec![1.0, 1.0], vec![1.0, 1.0]];
+        let border = BorderMode::default();
+        let average = custom(&img, &kernel, None, 0.0, None, Channels::Rgb, border).unwrap();
+        assert_eq!(average, img);
+        let brighter = custom(&img, &kernel, Some(2.0), 0.2, None, Channels::Rgb, border).unwrap();
+        assert_eq!(brighter.to_luma8().get_pixel(1, 1).0[0], 251);
+
+        assert!(custom(
+            &img,
+            &kernel,
+            None,
+            0.0,
+            Some((2, 0)),
+            Channels::Rgb,
+            border
+        )
+        .is_err());
+        assert!(custom(&img, &kernel, Some(0.0), 0.0, None, Channels::Rgb, border).is_err());
+        assert!(custom(
+            &img,
+            &[vec![1.0], vec![]],
+            None,
+            0.0,
+            None,
+            Channels::Rgb,
+            border
+        )
+        .is_err());
+    }
+
+    fn noise(width: u32, height: u32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| {
+            Luma([((x * 7919 + y * 104729) % 251) as u8])
+        })
+    }
+
+    #[test]
+    fn mean_filter_matches_summing_every_window() {
+        let img = noise(13, 9);
+        for name in BorderMode::NAMES {
+            let border = BorderMode::from_name(name).unwrap();
+            for kernel_size in [1, 3, 4, 7, 21] {
+                let r = kernel_size as i32 / 2;
+                let expected = GrayImage::from_fn(13, 9, |x, y| {
+                    let mut sum = 0.0;
+                    for dx in -r..=r {
+                        for dy in -r..=r {
+                            let pixel =
+                                get_pixel_grayscale(&img, x as i32 + dx, y as i32 + dy, border);
+                            sum += pixel as f64;
+                        }
+                    }
+                    Luma([u8::from_f64(sum / ((2 * r + 1) * (2 * r + 1)) as f64)])
+                });
+                assert_eq!(
+                    mean_filter(&img, kernel_size, border),
+                    expected,
+                    "{} {}",
+                    name,
+                    kernel_size
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn separable_gaussian_matches_the_2d_kernel() {
+        let img = noise(12, 10);
+        for name in BorderMode::NAMES {
+            let border = BorderMode::from_name(name).unwrap();
+            for (size, sigma) in [(3, 0.8), (5, 1.5), (4, 1.0), (9, 3.0)] {
+                let kernel = generate_gaussian_kernel(size, sigma);
+                let kernel_2d: Vec<Vec<f64>> = kernel
+                    .iter()
+                    .map(|a| kernel.iter().map(|b| a * b).collect())
+                    .collect();
+                let expected = apply_kernel_f64_gray(
+                    &img,
+                    &kernel_2d,
+                    ((size - 1) / 2, (size - 1) / 2),
+                    0.0,
+                    border,
+                );
+                let result = gaussian_filter(&img, &kernel, border);
+                for (a, b) in result.pixels().zip(expected.pixels()) {
+                    assert!(
+                        (a.0[0] as i32 - b.0[0] as i32).abs() <= 1,
+                        "{} {}",
+                        name,
+                        size
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn even_gaussian_kernels_do_not_shift_the_image() {
+        let img = DynamicImage::ImageLuma8(GrayImage::from_fn(9, 9, |x, y| {
+            Luma([if (x, y) == (4, 4) { 255 } else { 0 }])
+        }));
+        for size in [2, 4, 6] {
+            let blurred =
+                gaussian(&img, Some(size), 1.0, Channels::Rgb, BorderMode::Constant).to_luma8();
+            let peak = blurred
+                .enumerate_pixels()
+                .max_by_key(|(_, _, p)| p.0[0])
+                .unwrap();
+            assert_eq!((peak.0, peak.1), (4, 4), "{}", size);
+        }
+    }
+
+    #[test]
+    fn gaussian_kernel_size_defaults_to_three_sigma() {
+        let img = DynamicImage::ImageLuma8(noise(20, 20));
+        let border = BorderMode::default();
+        assert_eq!(
+            gaussian(&img, None, 2.0, Channels::Rgb, border),
+            gaussian(&img, Some(13), 2.0, Channels::Rgb, border)
+        );
+        assert_eq!(
+            gaussian(&img, None, 0.2, Channels::Rgb, border),
+            gaussian(&img, Some(3), 0.2, Channels::Rgb, border)
+        );
+    }
+
+    #[test]
+    fn histogram_rank_filter_matches_sorting() {
+        let img = noise(11, 8);
+        for name in BorderMode::NAMES {
+            let border = BorderMode::from_name(name).unwrap();
+            for r in [0, 1, 2, 5] {
+                let n = ((2 * r + 1) * (2 * r + 1)) as usize;
+                for k in [0, n / 4, n / 2, n - 1] {
+                    assert_eq!(
+                        histogram_rank_filter(&img, r, k, 256, border),
+                        sorting_rank_filter(&img, r, k, border),
+                        "{} r={} k={}",
+                        name,
+                        r,
+                        k
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn extreme_percentiles_are_min_and_max() {
+        let img = DynamicImage::ImageLuma8(noise(9, 9));
+        let border = BorderMode::Replicate;
+        let min = rank(&img, 3, 0.0, Channels::Rgb, border).to_luma8();
+        let max = rank(&img, 3, 100.0, Channels::Rgb, border).to_luma8();
+        let img = img.to_luma8();
+        for (x, y) in [(0, 0), (4, 4), (8, 3)] {
+            let window = (-1..=1).flat_map(|dx| {
+                let img = &img;
+                (-1..=1)
+                    .map(move |dy| get_pixel_grayscale(img, x as i32 + dx, y as i32 + dy, border))
+            });
+            assert_eq!(min.get_pixel(x, y).0[0], window.clone().min().unwrap());
+            assert_eq!(max.get_pixel(x, y).0[0], window.max().unwrap());
+        }
+    }
+}
@@ -1,4 +1,6 @@
+use crate::error::DipError;
 use crate::transform::color::invert;
+use crate::transform::registry::{ParamSpec, Registry};
 use image::{DynamicImage, GrayImage};
 use imageproc::contours::find_contours;
 use imageproc::drawing::draw_filled_rect_mut;
@@ -34,40 +36,44 @@ fn truncate_fft(fft: &Vec<Complex<f64>>, n: usize) -> Vec<Complex<f64>> {
     truncated_fft
 }
 
-pub fn reconstruct_impl(img: &GrayImage, nterms: usize) -> GrayImage {
-    let binary_img = imageproc::contrast::threshold(&img, 128);
+/// `(min_x, min_y, max_x, max_y)` of the points.
+fn bounds(points: &[Complex<f64>]) -> (f64, f64, f64, f64) {
+    points.iter().fold(
+        (
+            f64::INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NEG_INFINITY,
+        ),
+        |(min_x, min_y, max_x, max_y), p| {
+            (
+                min_x.min(p.re),
+                min_y.min(p.im),
+                max_x.max(p.re),
+                max_y.max(p.im),
+            )
+        },
+    )
+}
+
+pub fn reconstruct_impl(img: &GrayImage, nterms: usize) -> Result<GrayImage, DipError> {
+    let binary_img = imageproc::contrast::threshold(img, 128);
 
     // Find contours
     let contours = find_contours::<i32>(&binary_img);
     // get the contour with the most points
-    let contour = contours.iter().max_by_key(|c| c.points.len()).unwrap();
+    let contour = contours
+        .iter()
+        .max_by_key(|c| c.points.len())
+        .ok_or(DipError::EmptyContour)?;
     // generate a Complex<f64> vector with real parts as xs and imag parts as ys of points
     let points: Vec<Complex<f64>> = contour
         .points
         .iter()
         .map(|p| num_complex::Complex::new(p.x as f64, p.y as f64))
         .collect();
-    // get the max x and y
-    let max_x = points
-        .iter()
-        .map(|p| p.re)
-        .max_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap();
-    let max_y = points
-        .iter()
-        .map(|p| p.im)
-        .max_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap();
-    let min_x = points
-        .iter()
-        .map(|p| p.re)
-        .min_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap();
-    let min_y = points
-        .iter()
-        .map(|p| p.im)
-        .min_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap();
+    // get the bounding box
+    let (min_x, min_y, max_x, max_y) = bounds(&points);
     let x_scale = max_x - min_x;
     let y_scale = max_y - min_y;
     let max_scale = x_scale.max(y_scale);
@@ -75,35 +81,23 @@ pub fn reconstruct_impl(img: &GrayImage, nterms: usize) -> GrayImage {
     // fft
     let fd = fft(&points);
 
-    let truncated_fd = truncate_fft(&fd, nterms);
+    // a short contour has fewer terms than asked for, keep all of them
+    let truncated_fd = truncate_fft(&fd, nterms.min(fd.len()));
 
     let points_reconstructed = ifft(&truncated_fd);
 
     // scale the reconstructed points from (p_min, p_max) to 0-max(max_x, max_y)
-    let min_x_reconstructed = points_reconstructed
-        .iter()
-        .map(|p| p.re)
-        .min_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap();
-    let min_y_reconstructed = points_reconstructed
-        .iter()
-        .map(|p| p.im)
-        .min_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap();
-    let max_x_reconstructed = points_reconstructed
-        .iter()
-        .map(|p| p.re)
-        .max_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap();
-    let max_y_reconstructed = points_reconstructed
-        .iter()
-        .map(|p| p.im)
-        .max_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap();
+    let (min_x_reconstructed, min_y_reconstructed, max_x_reconstructed, max_y_reconstructed) =
+        bounds(&points_reconstructed);
 
     let x_scale_reconstructed = max_x_reconstructed - min_x_reconstructed;
     let y_scale_reconstructed = max_y_reconstructed - min_y_reconstructed;
     let max_scale_reconstructed = x_scale_reconstructed.max(y_scale_reconstructed);
+    // A single point or too few terms collapse the contour to a point,
+    // which can't be scaled back up
+    if max_scale_reconstructed == 0.0 {
+        return Err(DipError::EmptyContour);
+    }
 
     let points_reconstructed: Vec<Complex<f64>> = points_reconstructed
         .iter()
@@ -127,11 +121,19 @@ pub fn reconstruct_impl(img: &GrayImage, nterms: usize) -> GrayImage {
         );
     }
 
-    img_reconstructed
+    Ok(img_reconstructed)
 }
 
-pub fn reconstruct(img: &DynamicImage, nterms: usize) -> DynamicImage {
+pub fn reconstruct(img: &DynamicImage, nterms: usize) -> Result<DynamicImage, DipError> {
     let img = img.to_luma8();
-    let reconstructed = DynamicImage::ImageLuma8(reconstruct_impl(&img, nterms));
-    invert(reconstructed)
+    let reconstructed = DynamicImage::ImageLuma8(reconstruct_impl(&img, nterms)?);
+    Ok(invert(reconstructed))
+}
+
+pub(crate) fn register(registry: &mut Registry) {
+    registry.add(
+        "fourier_desc",
+        vec![ParamSpec::int("nterms", 1, 65535, Some(64))],
+        |img, args| reconstruct(img, args.int("nterms") as usize),
+    );
 }
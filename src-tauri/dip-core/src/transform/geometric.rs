@@ -0,0 +1,958 @@
+use image::{DynamicImage, GenericImageView, ImageBuffer, Pixel};
+
+use crate::error::DipError;
+
+use crate::transform::border::{border, border_param, BorderMode};
+use crate::transform::interpolation::{interpolation, interpolation_param, Interpolation};
+use crate::transform::registry::{Args, ParamSpec, Registry};
+use crate::transform::utils::{map_buffer, par_rows, rgb_to_ycbcr, Sample};
+
+/// `rgba`, given as fractions of white, in the channels of `P`. Gray pixels
+/// get the luma of the colour.
+fn colour<P>([r, g, b, a]: [f64; 4]) -> P
+where
+    P: Pixel,
+    P::Subpixel: Sample,
+{
+    let luma = rgb_to_ycbcr([r as f32, g as f32, b as f32])[0] as f64;
+    let values = match P::CHANNEL_COUNT {
+        1 => vec![luma],
+        2 => vec![luma, a],
+        3 => vec![r, g, b],
+        _ => vec![r, g, b, a],
+    };
+    let samples: Vec<P::Subpixel> = values
+        .into_iter()
+        .map(|value| Sample::round_from_f64(value * <P::Subpixel as Sample>::MAX))
+        .collect();
+    *P::from_slice(&samples)
+}
+
+/// Builds a `width` × `height` image whose pixel at (x, y) is the pixel of
+/// `image` at `source(x, y)`, or `fill` where that is `None`.
+fn remap<I, P>(
+    image: &I,
+    (width, height): (u32, u32),
+    fill: P,
+    source: impl Fn(i64, i64) -> Option<(u32, u32)> + Sync,
+) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    I: GenericImageView<Pixel = P> + Sync,
+    P: Pixel + Sync + 'static,
+    P::Subpixel: Sample,
+{
+    let mut out = ImageBuffer::new(width, height);
+    let channels = P::CHANNEL_COUNT as usize;
+
+    par_rows(&mut out, width as usize * channels, |y, row| {
+        for (x, pixel) in row.chunks_mut(channels).enumerate() {
+            *P::from_slice_mut(pixel) = match source(x as i64, y as i64) {
+                Some((old_x, old_y)) => image.get_pixel(old_x, old_y),
+                None => fill,
+            };
+        }
+    });
+
+    out
+}
+
+/// Like `remap`, but `source(x, y)` may fall between pixel centres, which lie
+/// on integer coordinates, and is interpolated there. Where it is `None` the
+/// pixel is `fill`.
+fn warp<I, P>(
+    image: &I,
+    (width, height): (u32, u32),
+    fill: P,
+    interpolation: Interpolation,
+    border: BorderMode,
+    source: impl Fn(f64, f64) -> Option<(f64, f64)> + Sync,
+) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    I: GenericImageView<Pixel = P> + Sync,
+    P: Pixel + Sync + 'static,
+    P::Subpixel: Sample,
+{
+    let mut out = ImageBuffer::new(width, height);
+    let channels = P::CHANNEL_COUNT as usize;
+
+    par_rows(&mut out, width as usize * channels, |y, row| {
+        for (x, pixel) in row.chunks_mut(channels).enumerate() {
+            *P::from_slice_mut(pixel) = match source(x as f64, y as f64) {
+                Some((old_x, old_y)) => interpolation.sample(image, old_x, old_y, border, fill),
+                None => fill,
+            };
+        }
+    });
+
+    out
+}
+
+fn translate_impl<I, P>(
+    image: &I,
+    x: i32,
+    y: i32,
+    border: BorderMode,
+) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    I: GenericImageView<Pixel = P> + Sync,
+    P: Pixel + Sync + 'static,
+    P::Subpixel: Sample,
+{
+    let dimensions = image.dimensions();
+    remap(image, dimensions, colour([0.0; 4]), |new_x, new_y| {
+        let old_x = new_x - x as i64;
+        let old_y = new_y - y as i64;
+        border.locate(old_x, old_y, dimensions)
+    })
+}
+
+/// Sine and cosine of `degrees`, exact for multiples of 90°.
+fn sin_cos_degrees(degrees: f64) -> (f64, f64) {
+    if degrees % 90.0 != 0.0 {
+        return degrees.to_radians().sin_cos();
+    }
+    match (degrees / 90.0).rem_euclid(4.0) as u8 {
+        0 => (0.0, 1.0),
+        1 => (1.0, 0.0),
+        2 => (0.0, -1.0),
+        _ => (-1.0, 0.0),
+    }
+}
+
+/// Rotates about `pivot`, or onto a canvas that fits the whole result if
+/// `expand` is set, in which case the pivot makes no difference.
+fn rotate_impl<I, P>(
+    image: &I,
+    angle: f64,
+    pivot: Option<(f64, f64)>,
+    expand: bool,
+    fill: P,
+    interpolation: Interpolation,
+    border: BorderMode,
+) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    I: GenericImageView<Pixel = P> + Sync,
+    P: Pixel + Sync + 'static,
+    P::Subpixel: Sample,
+{
+    let (width, height) = image.dimensions();
+    let (sin_angle, cos_angle) = sin_cos_degrees(angle);
+    let center = ((width as f64 - 1.0) / 2.0, (height as f64 - 1.0) / 2.0);
+
+    // The point `from` of the image ends up at `to` on the new canvas
+    let (size, from, to) = if expand {
+        let w = width as f64;
+        let h = height as f64;
+        let new_w = (w * cos_angle.abs() + h * sin_angle.abs() - 1e-6).ceil() as u32;
+        let new_h = (w * sin_angle.abs() + h * cos_angle.abs() - 1e-6).ceil() as u32;
+        let new_center = ((new_w as f64 - 1.0) / 2.0, (new_h as f64 - 1.0) / 2.0);
+        ((new_w, new_h), center, new_center)
+    } else {
+        let pivot = pivot.unwrap_or(center);
+        ((width, height), pivot, pivot)
+    };
+    let offset_x = from.0 - to.0 * cos_angle - to.1 * sin_angle;
+    let offset_y = from.1 + to.0 * sin_angle - to.1 * cos_angle;
+
+    let whole_pixels = [sin_angle, cos_angle, offset_x, offset_y]
+        .iter()
+        .all(|v| v.fract() == 0.0);
+    if whole_pixels {
+        // Quarter turns that map pixels onto pixels are just a permutation
+        let (sin_angle, cos_angle) = (sin_angle as i64, cos_angle as i64);
+        let (offset_x, offset_y) = (offset_x as i64, offset_y as i64);
+        return remap(image, size, fill, |x, y| {
+            let old_x = x * cos_angle + y * sin_angle + offset_x;
+            let old_y = -x * sin_angle + y * cos_angle + offset_y;
+            border.locate(old_x, old_y, (width, height))
+        });
+    }
+
+    warp(image, size, fill, interpolation, border, |x, y| {
+        let old_x = x * cos_angle + y * sin_angle + offset_x;
+        let old_y = -x * sin_angle + y * cos_angle + offset_y;
+        Some((old_x, old_y))
+    })
+}
+
+/// Resamples rows and then columns.
+fn resize_impl<I, P>(
+    image: &I,
+    width: u32,
+    height: u32,
+    interpolation: Interpolation,
+) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    I: GenericImageView<Pixel = P> + Sync,
+    P: Pixel + Sync + 'static,
+    P::Subpixel: Sample,
+{
+    let (old_w, old_h) = image.dimensions();
+    let mut out = ImageBuffer::new(width, height);
+    if old_w == 0 || old_h == 0 {
+        return out;
+    }
+    let channels = P::CHANNEL_COUNT as usize;
+
+    let x_taps = interpolation.resize_taps(old_w, width);
+    let row_len = width as usize * channels;
+    let mut rows = vec![0.0; row_len * old_h as usize];
+    par_rows(&mut rows, row_len, |y, row| {
+        for (pixel, (first, weights)) in row.chunks_mut(channels).zip(&x_taps) {
+            for (i, weight) in weights.iter().enumerate() {
+                let old = image.get_pixel((first + i) as u32, y as u32);
+                for (sum, value) in pixel.iter_mut().zip(old.channels()) {
+                    *sum += weight * value.into_f64();
+                }
+            }
+        }
+    });
+
+    let y_taps = interpolation.resize_taps(old_h, height);
+    par_rows(&mut out, row_len, |y, row| {
+        let (first, weights) = &y_taps[y];
+        for (x, value) in row.iter_mut().enumerate() {
+            let sum: f64 = weights
+                .iter()
+                .enumerate()
+                .map(|(j, weight)| weight * rows[(first + j) * row_len + x])
+                .sum();
+            *value = Sample::round_from_f64(sum);
+        }
+    });
+
+    out
+}
+
+/// Which way `mirror` flips the image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mirror {
+    /// Left to right.
+    X,
+    /// Top to bottom.
+    Y,
+    /// Both, which is the same as turning the image by 180°.
+    Both,
+    /// About the diagonal from the top left corner.
+    Transpose,
+    /// About the diagonal from the top right corner.
+    AntiTranspose,
+}
+
+impl Mirror {
+    pub const NAMES: [&'static str; 5] = ["x", "y", "both", "transpose", "anti_transpose"];
+
+    pub fn from_name(name: &str) -> Option<Mirror> {
+        match name {
+            "x" => Some(Mirror::X),
+            "y" => Some(Mirror::Y),
+            "both" => Some(Mirror::Both),
+            "transpose" => Some(Mirror::Transpose),
+            "anti_transpose" => Some(Mirror::AntiTranspose),
+            _ => None,
+        }
+    }
+}
+
+/// Copies whole pixels, or whole rows where they stay in one piece.
+fn mirror_impl<P>(
+    image: &ImageBuffer<P, Vec<P::Subpixel>>,
+    mirror: Mirror,
+) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel + 'static,
+    P::Subpixel: Sample,
+{
+    let (width, height) = image.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    let (new_w, new_h) = match mirror {
+        Mirror::Transpose | Mirror::AntiTranspose => (height, width),
+        _ => (width, height),
+    };
+    let channels = P::CHANNEL_COUNT as usize;
+    let src_row_len = w * channels;
+    let src = image.as_raw();
+    let src_row = |y: usize| &src[y * src_row_len..][..src_row_len];
+    // Index of the first sample of pixel (x, y) in `src`
+    let at = |x: usize, y: usize| (y * w + x) * channels;
+
+    let mut out = ImageBuffer::new(new_w, new_h);
+    par_rows(&mut out, new_w as usize * channels, |y, row| match mirror {
+        Mirror::X | Mirror::Both => {
+            let old_y = if mirror == Mirror::Both { h - 1 - y } else { y };
+            let pixels = src_row(old_y).chunks(channels).rev();
+            for (pixel, old) in row.chunks_mut(channels).zip(pixels) {
+                pixel.copy_from_slice(old);
+            }
+        }
+        Mirror::Y => row.copy_from_slice(src_row(h - 1 - y)),
+        Mirror::Transpose => {
+            for (x, pixel) in row.chunks_mut(channels).enumerate() {
+                pixel.copy_from_slice(&src[at(y, x)..][..channels]);
+            }
+        }
+        Mirror::AntiTranspose => {
+            for (x, pixel) in row.chunks_mut(channels).enumerate() {
+                pixel.copy_from_slice(&src[at(w - 1 - y, h - 1 - x)..][..channels]);
+            }
+        }
+    });
+    out
+}
+
+/// Maps each output pixel through `inverse` back into `image`.
+fn perspective_impl<I, P>(
+    image: &I,
+    inverse: &[[f64; 3]; 3],
+    interpolation: Interpolation,
+    border: BorderMode,
+) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    I: GenericImageView<Pixel = P> + Sync,
+    P: Pixel + Sync + 'static,
+    P::Subpixel: Sample,
+{
+    let [[a, b, c], [d, e, f], [g, h, i]] = *inverse;
+    warp(
+        image,
+        image.dimensions(),
+        colour([0.0; 4]),
+        interpolation,
+        border,
+        |x, y| {
+            let w = g * x + h * y + i;
+            // Points on or beyond the horizon have no source
+            if w <= f64::EPSILON {
+                return None;
+            }
+            Some(((a * x + b * y + c) / w, (d * x + e * y + f) / w))
+        },
+    )
+}
+
+fn invert(m: [[f64; 3]; 3]) -> Option<[[f64; 3]; 3]> {
+    let [[a, b, c], [d, e, f], [g, h, i]] = m;
+    let cofactors = [
+        [e * i - f * h, c * h - b * i, b * f - c * e],
+        [f * g - d * i, a * i - c * g, c * d - a * f],
+        [d * h - e * g, b * g - a * h, a * e - b * d],
+    ];
+    let det = a * cofactors[0][0] + b * cofactors[1][0] + c * cofactors[2][0];
+    if det.abs() < 1e-12 || !det.is_finite() {
+        return None;
+    }
+    Some(cofactors.map(|row| row.map(|v| v / det)))
+}
+
+fn stretch_impl<I, P>(
+    image: &I,
+    x: f32,
+    y: f32,
+    interpolation: Interpolation,
+) -> Result<ImageBuffer<P, Vec<P::Subpixel>>, DipError>
+where
+    I: GenericImageView<Pixel = P> + Sync,
+    P: Pixel + Sync + 'static,
+    P::Subpixel: Sample,
+{
+    let (width, height) = image.dimensions();
+    let new_w = (width as f32 * x).round() as u32;
+    let new_h = (height as f32 * y).round() as u32;
+    if new_w == 0 || new_h == 0 {
+        return Err(DipError::InvalidArgument(format!(
+            "Stretching the {}x{} image by {} x {} leaves no pixels",
+            width, height, x, y
+        )));
+    }
+
+    Ok(resize_impl(image, new_w, new_h, interpolation))
+}
+
+/// Puts `image` on a `width` × `height` canvas with its top left corner at
+/// `offset`, which may be outside of the canvas.
+fn place_impl<I, P>(
+    image: &I,
+    size: (u32, u32),
+    (left, top): (i64, i64),
+    border: BorderMode,
+    fill: P,
+) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    I: GenericImageView<Pixel = P> + Sync,
+    P: Pixel + Sync + 'static,
+    P::Subpixel: Sample,
+{
+    let dimensions = image.dimensions();
+    remap(image, size, fill, |x, y| {
+        border.locate(x - left, y - top, dimensions)
+    })
+}
+
+pub fn translate(
+    img: DynamicImage,
+    x: Option<i32>,
+    y: Option<i32>,
+    border: BorderMode,
+) -> DynamicImage {
+    let x = x.unwrap_or(0);
+    let y = y.unwrap_or(0);
+    map_buffer!(&img, |img| translate_impl(img, x, y, border))
+}
+
+/// Moves the pixel at (x, y) to (a x + b y + c, d x + e y + f), where `matrix`
+/// is `[[a, b, c], [d, e, f]]`, keeping the size of the image. Pixel centres
+/// lie on integer coordinates.
+pub fn affine(
+    img: DynamicImage,
+    matrix: [[f64; 3]; 2],
+    interpolation: Interpolation,
+    border: BorderMode,
+) -> Result<DynamicImage, DipError> {
+    let [top, middle] = matrix;
+    perspective(img, [top, middle, [0.0, 0.0, 1.0]], interpolation, border)
+}
+
+/// Moves the pixel at (x, y) to the point that `homography` maps the
+/// homogeneous coordinates (x, y, 1) to, keeping the size of the image.
+pub fn perspective(
+    img: DynamicImage,
+    homography: [[f64; 3]; 3],
+    interpolation: Interpolation,
+    border: BorderMode,
+) -> Result<DynamicImage, DipError> {
+    let inverse = invert(homography)
+        .ok_or_else(|| DipError::InvalidArgument("The matrix is not invertible".to_string()))?;
+    Ok(map_buffer!(&img, |img| perspective_impl(
+        img,
+        &inverse,
+        interpolation,
+        border
+    )))
+}
+
+/// The homography that maps each point of `from` to the same one of `to`, for
+/// example the corners of a photographed page to those of a rectangle.
+pub fn homography_from_points(
+    from: [[f64; 2]; 4],
+    to: [[f64; 2]; 4],
+) -> Result<[[f64; 3]; 3], DipError> {
+    // With the last element fixed at 1, each pair of points gives two linear
+    // equations in the other eight
+    let mut rows = [[0.0; 9]; 8];
+    for (k, ([x, y], [u, v])) in from.into_iter().zip(to).enumerate() {
+        rows[2 * k] = [x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, u];
+        rows[2 * k + 1] = [0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, v];
+    }
+    let h = solve(rows).ok_or_else(|| {
+        DipError::InvalidArgument("No three of the points may lie on one line".to_string())
+    })?;
+    Ok([[h[0], h[1], h[2]], [h[3], h[4], h[5]], [h[6], h[7], 1.0]])
+}
+
+/// Solves the linear system whose augmented matrix is `rows` by Gaussian
+/// elimination, or `None` if it is singular.
+fn solve<const N: usize, const M: usize>(mut rows: [[f64; M]; N]) -> Option<[f64; N]> {
+    let scale = rows
+        .iter()
+        .flat_map(|row| row[..N].iter())
+        .fold(0.0f64, |max, v| max.max(v.abs()));
+    for col in 0..N {
+        let mut pivot = col;
+        for row in col + 1..N {
+            if rows[row][col].abs() > rows[pivot][col].abs() {
+                pivot = row;
+            }
+        }
+        if rows[pivot][col].abs() <= scale * 1e-12 {
+            return None;
+        }
+        rows.swap(col, pivot);
+        let (done, rest) = rows.split_at_mut(col + 1);
+        let pivot_row = &done[col];
+        for row in rest {
+            let factor = row[col] / pivot_row[col];
+            for (value, above) in row[col..].iter_mut().zip(&pivot_row[col..]) {
+                *value -= factor * above;
+            }
+        }
+    }
+    let mut solution = [0.0; N];
+    for row in (0..N).rev() {
+        let known: f64 = (row + 1..N).map(|k| rows[row][k] * solution[k]).sum();
+        solution[row] = (rows[row][N] - known) / rows[row][row];
+    }
+    Some(solution)
+}
+
+/// The same image with an alpha channel, which it may already have.
+fn with_alpha(img: DynamicImage) -> DynamicImage {
+    match img {
+        DynamicImage::ImageLuma8(_) => DynamicImage::ImageLumaA8(img.to_luma_alpha8()),
+        DynamicImage::ImageLuma16(_) => DynamicImage::ImageLumaA16(img.to_luma_alpha16()),
+        DynamicImage::ImageRgb16(_) => DynamicImage::ImageRgba16(img.to_rgba16()),
+        DynamicImage::ImageRgb32F(_) => DynamicImage::ImageRgba32F(img.to_rgba32f()),
+        img if img.color().has_alpha() => img,
+        img => DynamicImage::ImageRgba8(img.to_rgba8()),
+    }
+}
+
+/// Rotates clockwise by `angle` degrees about `pivot`, the centre of the
+/// image by default. Pixel centres lie on integer coordinates. Where the
+/// border is constant, pixels from outside of the image are `fill`, RGBA as
+/// fractions of white; a translucent fill adds an alpha channel.
+pub fn rotate(
+    img: DynamicImage,
+    angle: Option<f32>,
+    pivot: Option<(f64, f64)>,
+    expand: bool,
+    fill: [f64; 4],
+    interpolation: Interpolation,
+    border: BorderMode,
+) -> DynamicImage {
+    let angle = angle.unwrap_or(0.0) as f64;
+    let img = if border == BorderMode::Constant && fill[3] < 1.0 {
+        with_alpha(img)
+    } else {
+        img
+    };
+    map_buffer!(&img, |img| rotate_impl(
+        img,
+        angle,
+        pivot,
+        expand,
+        colour(fill),
+        interpolation,
+        border
+    ))
+}
+
+pub fn resize(
+    img: DynamicImage,
+    width: Option<u32>,
+    height: Option<u32>,
+    interpolation: Interpolation,
+) -> DynamicImage {
+    let width = width.unwrap_or(img.width());
+    let height = height.unwrap_or(img.height());
+    map_buffer!(&img, |img| resize_impl(img, width, height, interpolation))
+}
+
+/// Flips the image as `axis`, one of `Mirror::NAMES`, says.
+pub fn mirror(img: DynamicImage, axis: &str) -> Result<DynamicImage, DipError> {
+    let mirror = Mirror::from_name(axis)
+        .ok_or_else(|| DipError::InvalidArgument(format!("Unknown mirror axis \"{}\"", axis)))?;
+    Ok(map_buffer!(&img, |img| mirror_impl(img, mirror)))
+}
+
+pub fn stretch(
+    img: DynamicImage,
+    x: Option<f32>,
+    y: Option<f32>,
+    interpolation: Interpolation,
+) -> Result<DynamicImage, DipError> {
+    let x = x.unwrap_or(1.0);
+    let y = y.unwrap_or(1.0);
+    Ok(map_buffer!(&img, |img| stretch_impl(
+        img,
+        x,
+        y,
+        interpolation
+    )?))
+}
+
+/// Cuts out the `width` × `height` region whose top left corner is (x, y).
+pub fn crop(
+    img: &DynamicImage,
+    (x, y): (u32, u32),
+    (width, height): (u32, u32),
+) -> Result<DynamicImage, DipError> {
+    let fits = |start: u32, len: u32, max: u32| len > 0 && start as u64 + len as u64 <= max as u64;
+    if !fits(x, width, img.width()) || !fits(y, height, img.height()) {
+        return Err(DipError::InvalidArgument(format!(
+            "The region {}x{} at ({}, {}) is not inside the {}x{} image",
+            width,
+            height,
+            x,
+            y,
+            img.width(),
+            img.height()
+        )));
+    }
+    Ok(img.crop_imm(x, y, width, height))
+}
+
+/// Adds `top`, `bottom`, `left` and `right` pixels around the image, made up
+/// by `border`. With a constant border they are `fill`, RGBA as fractions of
+/// white; a translucent fill adds an alpha channel.
+pub fn pad(
+    img: DynamicImage,
+    [top, bottom, left, right]: [u32; 4],
+    border: BorderMode,
+    fill: [f64; 4],
+) -> DynamicImage {
+    let img = if border == BorderMode::Constant && fill[3] < 1.0 {
+        with_alpha(img)
+    } else {
+        img
+    };
+    let width = img.width() + left + right;
+    let height = img.height() + top + bottom;
+    let offset = (left as i64, top as i64);
+    map_buffer!(&img, |img| place_impl(
+        img,
+        (width, height),
+        offset,
+        border,
+        colour(fill)
+    ))
+}
+
+/// Where the image goes when the canvas around it is resized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    pub const NAMES: [&'static str; 9] = [
+        "top_left",
+        "top",
+        "top_right",
+        "left",
+        "center",
+        "right",
+        "bottom_left",
+        "bottom",
+        "bottom_right",
+    ];
+
+    pub fn from_name(name: &str) -> Option<Anchor> {
+        match name {
+            "top_left" => Some(Anchor::TopLeft),
+            "top" => Some(Anchor::Top),
+            "top_right" => Some(Anchor::TopRight),
+            "left" => Some(Anchor::Left),
+            "center" => Some(Anchor::Center),
+            "right" => Some(Anchor::Right),
+            "bottom_left" => Some(Anchor::BottomLeft),
+            "bottom" => Some(Anchor::Bottom),
+            "bottom_right" => Some(Anchor::BottomRight),
+            _ => None,
+        }
+    }
+
+    /// Where the image starts along each axis, in halves of the space left
+    /// over on the canvas.
+    fn fractions(self) -> (i64, i64) {
+        match self {
+            Anchor::TopLeft => (0, 0),
+            Anchor::Top => (1, 0),
+            Anchor::TopRight => (2, 0),
+            Anchor::Left => (0, 1),
+            Anchor::Center => (1, 1),
+            Anchor::Right => (2, 1),
+            Anchor::BottomLeft => (0, 2),
+            Anchor::Bottom => (1, 2),
+            Anchor::BottomRight => (2, 2),
+        }
+    }
+}
+
+/// Changes the size of the canvas without scaling the image, which keeps its
+/// place at `anchor` and is cut off where the canvas is smaller. New pixels
+/// are `fill`, RGBA as fractions of white; a translucent fill adds an alpha
+/// channel.
+pub fn resize_canvas(
+    img: DynamicImage,
+    (width, height): (u32, u32),
+    anchor: Anchor,
+    fill: [f64; 4],
+) -> DynamicImage {
+    let img = if fill[3] < 1.0 { with_alpha(img) } else { img };
+    let (x, y) = anchor.fractions();
+    let left = (width as i64 - img.width() as i64) * x / 2;
+    let top = (height as i64 - img.height() as i64) * y / 2;
+    map_buffer!(&img, |img| place_impl(
+        img,
+        (width, height),
+        (left, top),
+        BorderMode::Constant,
+        colour(fill)
+    ))
+}
+
+/// Reads a colour given as `gray`, `r g b` or `r g b a` from 0 to 255.
+fn fill(args: &Args) -> Result<[f64; 4], DipError> {
+    let values = match args.matrix("fill") {
+        [row] => row,
+        _ => &[][..],
+    };
+    let [r, g, b, a] = match *values {
+        [v] => [v, v, v, 255.0],
+        [r, g, b] => [r, g, b, 255.0],
+        [r, g, b, a] => [r, g, b, a],
+        _ => {
+            return Err(DipError::InvalidArgument(
+                "fill must be 1, 3 or 4 values on one row".to_string(),
+            ))
+        }
+    };
+    let rgba = [r, g, b, a];
+    if rgba.iter().any(|v| !(0.0..=255.0).contains(v)) {
+        return Err(DipError::InvalidArgument(
+            "fill values must be between 0 and 255".to_string(),
+        ));
+    }
+    Ok(rgba.map(|v| v / 255.0))
+}
+
+/// Reads `matrix` as a 2×3 affine matrix, to which a 0 0 1 row may be added.
+fn affine_matrix(args: &Args) -> Result<[[f64; 3]; 2], DipError> {
+    match args.matrix("matrix") {
+        [top, middle, ..] if top.len() == 3 => {
+            let last = &args.matrix("matrix")[2..];
+            if !last.is_empty() && last != [vec![0.0, 0.0, 1.0]] {
+                return Err(DipError::InvalidArgument(
+                    "The third row of an affine matrix must be 0 0 1".to_string(),
+                ));
+            }
+            Ok([[top[0], top[1], top[2]], [middle[0], middle[1], middle[2]]])
+        }
+        _ => Err(DipError::InvalidArgument(
+            "The affine matrix must have 2 rows of 3".to_string(),
+        )),
+    }
+}
+
+/// Reads `matrix` as either a 3×3 homography or four rows of `x y x' y'`,
+/// each a point and where it should end up.
+fn homography(args: &Args) -> Result<[[f64; 3]; 3], DipError> {
+    let rows = args.matrix("matrix");
+    match (rows.len(), rows.first().map_or(0, Vec::len)) {
+        (3, 3) => Ok([0, 1, 2].map(|i| [rows[i][0], rows[i][1], rows[i][2]])),
+        (4, 4) => {
+            let from = [0, 1, 2, 3].map(|i| [rows[i][0], rows[i][1]]);
+            let to = [0, 1, 2, 3].map(|i| [rows[i][2], rows[i][3]]);
+            homography_from_points(from, to)
+        }
+        _ => Err(DipError::InvalidArgument(
+            "Expected a 3×3 matrix or 4 rows of x y x' y'".to_string(),
+        )),
+    }
+}
+
+pub(crate) fn register(registry: &mut Registry) {
+    registry.add(
+        "geometric/translate",
+        vec![
+            ParamSpec::int("x", i32::MIN as i64, i32::MAX as i64, Some(0)),
+            ParamSpec::int("y", i32::MIN as i64, i32::MAX as i64, Some(0)),
+            border_param(),
+        ],
+        |img, args| {
+            Ok(translate(
+                img.clone(),
+                Some(args.int("x") as i32),
+                Some(args.int("y") as i32),
+                border(args),
+            ))
+        },
+    );
+    registry.add(
+        "geometric/rotate",
+        vec![
+            ParamSpec::float("angle", -360.0, 360.0, Some(0.0)),
+            border_param(),
+            interpolation_param(),
+            ParamSpec::choice("canvas", &["keep", "expand"], Some("keep")),
+            // -1 for the centre
+            ParamSpec::float("pivot_x", -1.0, 65535.0, Some(-1.0)),
+            ParamSpec::float("pivot_y", -1.0, 65535.0, Some(-1.0)),
+            ParamSpec::matrix("fill", Some(vec![vec![0.0, 0.0, 0.0, 255.0]])),
+        ],
+        |img, args| {
+            let center = |len: u32, pivot: f64| {
+                if pivot < 0.0 {
+                    (len as f64 - 1.0) / 2.0
+                } else {
+                    pivot
+                }
+            };
+            let pivot = (
+                center(img.width(), args.float("pivot_x")),
+                center(img.height(), args.float("pivot_y")),
+            );
+            Ok(rotate(
+                img.clone(),
+                Some(args.float("angle") as f32),
+                Some(pivot),
+                args.str("canvas") == "expand",
+                fill(args)?,
+                interpolation(args),
+                border(args),
+            ))
+        },
+    );
+    registry.add(
+        "geometric/resize",
+        vec![
+            ParamSpec::int("width", 1, 65535, None),
+            ParamSpec::int("height", 1, 65535, None),
+            interpolation_param(),
+        ],
+        |img, args| {
+            Ok(resize(
+                img.clone(),
+                Some(args.int("width") as u32),
+                Some(args.int("height") as u32),
+                interpolation(args),
+            ))
+        },
+    );
+    registry.add(
+        "geometric/mirror",
+        vec![ParamSpec::choice("axis", &Mirror::NAMES, None)],
+        |img, args| mirror(img.clone(), args.str("axis")),
+    );
+    registry.add(
+        "geometric/stretch",
+        vec![
+            ParamSpec::float("x", 0.01, 100.0, Some(1.0)),
+            ParamSpec::float("y", 0.01, 100.0, Some(1.0)),
+            interpolation_param(),
+        ],
+        |img, args| {
+            stretch(
+                img.clone(),
+                Some(args.float("x") as f32),
+                Some(args.float("y") as f32),
+                interpolation(args),
+            )
+        },
+    );
+    registry.add(
+        "geometric/affine",
+        vec![
+            ParamSpec::matrix("matrix", None),
+            interpolation_param(),
+            border_param(),
+        ],
+        |img, args| {
+            affine(
+                img.clone(),
+                affine_matrix(args)?,
+                interpolation(args),
+                border(args),
+            )
+        },
+    );
+    registry.add(
+        "geometric/perspective",
+        vec![
+            ParamSpec::matrix("matrix", None),
+            interpolation_param(),
+            border_param(),
+        ],
+        |img, args| {
+            perspective(
+                img.clone(),
+                homography(args)?,
+                interpolation(args),
+                border(args),
+            )
+        },
+    );
+    registry.add(
+        "geometric/crop",
+        vec![
+            ParamSpec::int("x", 0, 65535, Some(0)),
+            ParamSpec::int("y", 0, 65535, Some(0)),
+            ParamSpec::int("width", 1, 65535, None),
+            ParamSpec::int("height", 1, 65535, None),
+        ],
+        |img, args| {
+            crop(
+                img,
+                (args.int("x") as u32, args.int("y") as u32),
+                (args.int("width") as u32, args.int("height") as u32),
+            )
+        },
+    );
+    registry.add(
+        "geometric/pad",
+        vec![
+            ParamSpec::int("top", 0, 65535, Some(0)),
+            ParamSpec::int("bottom", 0, 65535, Some(0)),
+            ParamSpec::int("left", 0, 65535, Some(0)),
+            ParamSpec::int("right", 0, 65535, Some(0)),
+            border_param(),
+            ParamSpec::matrix("fill", Some(vec![vec![0.0, 0.0, 0.0, 255.0]])),
+        ],
+        |img, args| {
+            let sides = ["top", "bottom", "left", "right"].map(|side| args.int(side) as u32);
+            Ok(pad(img.clone(), sides, border(args), fill(args)?))
+        },
+    );
+    registry.add(
+        "geometric/canvas",
+        vec![
+            ParamSpec::int("width", 1, 65535, None),
+            ParamSpec::int("height", 1, 65535, None),
+            ParamSpec::choice("anchor", &Anchor::NAMES, Some("center")),
+            ParamSpec::matrix("fill", Some(vec![vec![0.0, 0.0, 0.0, 255.0]])),
+        ],
+        |img, args| {
+            let anchor = Anchor::from_name(args.str("anchor")).unwrap_or(Anchor::Center);
+            Ok(resize_canvas(
+                img.clone(),
+                (args.int("width") as u32, args.int("height") as u32),
+                anchor,
+                fill(args)?,
+            ))
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(h: &[[f64; 3]; 3], [x, y]: [f64; 2]) -> [f64; 2] {
+        let w = h[2][0] * x + h[2][1] * y + h[2][2];
+        [
+            (h[0][0] * x + h[0][1] * y + h[0][2]) / w,
+            (h[1][0] * x + h[1][1] * y + h[1][2]) / w,
+        ]
+    }
+
+    #[test]
+    fn homographies_map_the_given_points() {
+        let from = [[10.0, 12.0], [300.0, 40.0], [280.0, 390.0], [5.0, 350.0]];
+        let to = [[0.0, 0.0], [299.0, 0.0], [299.0, 399.0], [0.0, 399.0]];
+        let h = homography_from_points(from, to).unwrap();
+        for (from, to) in from.into_iter().zip(to) {
+            let [x, y] = apply(&h, from);
+            assert!((x - to[0]).abs() < 1e-6 && (y - to[1]).abs() < 1e-6);
+        }
+
+        let inverse = invert(h).unwrap();
+        let [x, y] = apply(&inverse, to[2]);
+        assert!((x - from[2][0]).abs() < 1e-6 && (y - from[2][1]).abs() < 1e-6);
+    }
+
+    #[test]
+    fn collinear_points_have_no_homography() {
+        let from = [[0.0, 0.0], [1.0, 1.0], [2.0, 2.0], [0.0, 5.0]];
+        let to = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+        assert!(homography_from_points(from, to).is_err());
+        assert!(invert([[1.0, 2.0, 0.0], [2.0, 4.0, 0.0], [0.0, 0.0, 1.0]]).is_none());
+    }
+}
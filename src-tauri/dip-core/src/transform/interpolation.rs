@@ -0,0 +1,243 @@
+use image::{GenericImageView, Pixel};
+use std::f64::consts::PI;
+
+use crate::transform::border::BorderMode;
+use crate::transform::registry::{Args, ParamSpec};
+use crate::transform::utils::Sample;
+
+/// How pixel values between the pixel centres are made up when an image is
+/// resampled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Nearest,
+    Bilinear,
+    /// Keys' cubic convolution with a = -0.5.
+    Bicubic,
+    /// Windowed sinc over three lobes.
+    Lanczos3,
+}
+
+impl Default for Interpolation {
+    fn default() -> Self {
+        Interpolation::Bilinear
+    }
+}
+
+/// Most taps `point_taps` can return, for Lanczos-3.
+const MAX_TAPS: usize = 6;
+
+impl Interpolation {
+    pub const NAMES: [&'static str; 4] = ["nearest", "bilinear", "bicubic", "lanczos3"];
+
+    pub fn from_name(name: &str) -> Option<Interpolation> {
+        match name {
+            "nearest" => Some(Interpolation::Nearest),
+            "bilinear" => Some(Interpolation::Bilinear),
+            "bicubic" => Some(Interpolation::Bicubic),
+            "lanczos3" => Some(Interpolation::Lanczos3),
+            _ => None,
+        }
+    }
+
+    /// How far the kernel reaches from the sample point, in pixels.
+    pub fn support(self) -> f64 {
+        match self {
+            Interpolation::Nearest => 0.5,
+            Interpolation::Bilinear => 1.0,
+            Interpolation::Bicubic => 2.0,
+            Interpolation::Lanczos3 => 3.0,
+        }
+    }
+
+    /// Weight of a pixel `t` pixels away from the sample point.
+    pub fn weight(self, t: f64) -> f64 {
+        let t = t.abs();
+        match self {
+            Interpolation::Nearest if t < 0.5 => 1.0,
+            Interpolation::Nearest => 0.0,
+            Interpolation::Bilinear => (1.0 - t).max(0.0),
+            Interpolation::Bicubic if t < 1.0 => (1.5 * t - 2.5) * t * t + 1.0,
+            Interpolation::Bicubic if t < 2.0 => ((-0.5 * t + 2.5) * t - 4.0) * t + 2.0,
+            Interpolation::Bicubic => 0.0,
+            Interpolation::Lanczos3 if t < 3.0 => sinc(t) * sinc(t / 3.0),
+            Interpolation::Lanczos3 => 0.0,
+        }
+    }
+
+    /// Calls `push` with the weight of every pixel along one axis that
+    /// contributes to the value at `x`, and returns the index of the first.
+    /// With `scale` above 1 the kernel is stretched to average over that many
+    /// pixels, which is how downscaling avoids aliasing. Nearest neighbour is
+    /// never stretched.
+    fn taps(self, x: f64, scale: f64, mut push: impl FnMut(f64)) -> i64 {
+        if self == Interpolation::Nearest {
+            push(1.0);
+            return (x + 0.5).floor() as i64;
+        }
+        let support = self.support() * scale;
+        let first = (x - support).floor() as i64 + 1;
+        let last = (x + support).floor() as i64;
+        for i in first..=last {
+            push(self.weight((i as f64 - x) / scale));
+        }
+        first
+    }
+
+    /// `taps` without stretching, which fit in an array.
+    fn point_taps(self, x: f64) -> (i64, usize, [f64; MAX_TAPS]) {
+        let mut weights = [0.0; MAX_TAPS];
+        let mut count = 0;
+        let first = self.taps(x, 1.0, |weight| {
+            weights[count] = weight;
+            count += 1;
+        });
+        (first, count, weights)
+    }
+
+    /// For each of `dst_len` pixels along an axis resized from `src_len`, the
+    /// first source pixel and the normalised weights of it and the ones after.
+    /// Taps past the ends are left out rather than repeating the edge pixel,
+    /// which would give it too much weight when downscaling.
+    pub(crate) fn resize_taps(self, src_len: u32, dst_len: u32) -> Vec<(usize, Vec<f64>)> {
+        let ratio = src_len as f64 / dst_len as f64;
+        let scale = ratio.max(1.0);
+        let last = src_len as i64 - 1;
+        (0..dst_len)
+            .map(|i| {
+                let mut weights = vec![];
+                let first = self.taps((i as f64 + 0.5) * ratio - 0.5, scale, |weight| {
+                    weights.push(weight)
+                });
+                let skip = (-first).max(0) as usize;
+                let first = first.max(0);
+                let mut weights: Vec<f64> = weights
+                    .into_iter()
+                    .skip(skip)
+                    .take((last - first + 1) as usize)
+                    .collect();
+                let total: f64 = weights.iter().sum();
+                weights.iter_mut().for_each(|weight| *weight /= total);
+                (first as usize, weights)
+            })
+            .collect()
+    }
+
+    /// Interpolates `image` at (x, y), with pixel centres on integer
+    /// coordinates. Pixels that `border` leaves out count as `fill`.
+    pub(crate) fn sample<I, P>(self, image: &I, x: f64, y: f64, border: BorderMode, fill: P) -> P
+    where
+        I: GenericImageView<Pixel = P>,
+        P: Pixel,
+        P::Subpixel: Sample,
+    {
+        let dimensions = image.dimensions();
+        if self == Interpolation::Nearest {
+            let x = (x + 0.5).floor() as i64;
+            let y = (y + 0.5).floor() as i64;
+            return match border.locate(x, y, dimensions) {
+                Some((x, y)) => image.get_pixel(x, y),
+                None => fill,
+            };
+        }
+
+        let (first_x, count_x, weights_x) = self.point_taps(x);
+        let (first_y, count_y, weights_y) = self.point_taps(y);
+        let channels = P::CHANNEL_COUNT as usize;
+        let mut sums = [0.0; 4];
+        let mut total = 0.0;
+        for (j, weight_y) in weights_y[..count_y].iter().enumerate() {
+            let row = border.index(first_y + j as i64, dimensions.1);
+            for (i, weight_x) in weights_x[..count_x].iter().enumerate() {
+                let weight = weight_x * weight_y;
+                total += weight;
+                let column = border.index(first_x + i as i64, dimensions.0);
+                let pixel = match (column, row) {
+                    (Some(column), Some(row)) => image.get_pixel(column, row),
+                    _ => fill,
+                };
+                for (sum, value) in sums.iter_mut().zip(pixel.channels()) {
+                    *sum += weight * value.into_f64();
+                }
+            }
+        }
+
+        let mut values = [<P::Subpixel as Sample>::from_f64(0.0); 4];
+        for (value, sum) in values.iter_mut().zip(sums) {
+            *value = Sample::round_from_f64(sum / total);
+        }
+        *P::from_slice(&values[..channels])
+    }
+}
+
+fn sinc(t: f64) -> f64 {
+    if t == 0.0 {
+        1.0
+    } else {
+        let t = PI * t;
+        t.sin() / t
+    }
+}
+
+pub(crate) fn interpolation_param() -> ParamSpec {
+    ParamSpec::choice("interpolation", &Interpolation::NAMES, Some("bilinear"))
+}
+
+pub(crate) fn interpolation(args: &Args) -> Interpolation {
+    Interpolation::from_name(args.str("interpolation")).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    #[test]
+    fn kernels_are_one_at_the_centre_and_zero_at_other_pixels() {
+        for name in Interpolation::NAMES {
+            let interpolation = Interpolation::from_name(name).unwrap();
+            assert_eq!(interpolation.weight(0.0), 1.0, "{}", name);
+            for t in 1..4 {
+                assert!(interpolation.weight(t as f64).abs() < 1e-12, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn sampling_at_pixel_centres_gives_the_pixels() {
+        let img = GrayImage::from_fn(7, 5, |x, y| Luma([(x * 30 + y * 7) as u8]));
+        for name in Interpolation::NAMES {
+            let interpolation = Interpolation::from_name(name).unwrap();
+            for (x, y, pixel) in img.enumerate_pixels() {
+                let sampled = interpolation.sample(
+                    &img,
+                    x as f64,
+                    y as f64,
+                    BorderMode::Replicate,
+                    Luma([0]),
+                );
+                assert_eq!(sampled, *pixel, "{} at ({}, {})", name, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn bilinear_sampling_blends_the_neighbours() {
+        let img = GrayImage::from_raw(2, 2, vec![0, 100, 200, 40]).unwrap();
+        let bilinear =
+            |x, y, border| Interpolation::Bilinear.sample(&img, x, y, border, Luma([60]));
+        assert_eq!(bilinear(0.5, 0.5, BorderMode::Replicate), Luma([85]));
+        assert_eq!(bilinear(0.25, 0.0, BorderMode::Replicate), Luma([25]));
+        assert_eq!(bilinear(-0.5, 0.0, BorderMode::Constant), Luma([30]));
+        assert_eq!(bilinear(-2.0, 0.0, BorderMode::Constant), Luma([60]));
+    }
+
+    #[test]
+    fn downscaling_averages_over_the_covered_pixels() {
+        let taps = Interpolation::Bilinear.resize_taps(8, 2);
+        assert_eq!(taps.len(), 2);
+        let (first, weights) = &taps[0];
+        assert_eq!(*first, 0);
+        assert!(weights.len() > 4, "only {} taps", weights.len());
+        assert!((weights.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+    }
+}
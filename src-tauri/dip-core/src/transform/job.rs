@@ -0,0 +1,132 @@
+use serde::Serialize;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::error::DipError;
+
+/// How far a job has got. Transforms are made of one or more parallel
+/// passes over the image; `step` counts the passes started so far and
+/// `done` out of `total` are the bands of rows finished in the current one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Progress {
+    pub step: usize,
+    pub done: usize,
+    pub total: usize,
+}
+
+type ProgressFn = Box<dyn Fn(Progress) + Send + Sync>;
+
+/// A transform running in the background, which reports its progress and
+/// can be cancelled from another thread.
+pub struct Job {
+    cancelled: AtomicBool,
+    step: AtomicUsize,
+    done: AtomicUsize,
+    total: AtomicUsize,
+    on_progress: ProgressFn,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<Job>>> = const { RefCell::new(None) };
+}
+
+impl Job {
+    /// `on_progress` is called from the worker threads after every band.
+    pub fn new(on_progress: impl Fn(Progress) + Send + Sync + 'static) -> Arc<Job> {
+        Arc::new(Job {
+            cancelled: AtomicBool::new(false),
+            step: AtomicUsize::new(0),
+            done: AtomicUsize::new(0),
+            total: AtomicUsize::new(0),
+            on_progress: Box::new(on_progress),
+        })
+    }
+
+    /// Asks the job to stop. Parallel passes skip their remaining bands and
+    /// `run` returns `DipError::Cancelled`.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Runs `f` on the calling thread with this as the current job.
+    pub fn run<R>(
+        self: &Arc<Self>,
+        f: impl FnOnce() -> Result<R, DipError>,
+    ) -> Result<R, DipError> {
+        let previous = CURRENT.with(|current| current.replace(Some(self.clone())));
+        let result = f();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+        if self.is_cancelled() {
+            return Err(DipError::Cancelled);
+        }
+        result
+    }
+
+    pub(crate) fn start_step(&self, total: usize) {
+        self.done.store(0, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+        self.step.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn band_done(&self) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        (self.on_progress)(Progress {
+            step: self.step.load(Ordering::Relaxed),
+            done,
+            total: self.total.load(Ordering::Relaxed),
+        });
+    }
+}
+
+/// The job that the calling thread is running, if any.
+pub(crate) fn current() -> Option<Arc<Job>> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::utils::par_rows;
+    use std::sync::Mutex;
+
+    #[test]
+    fn progress_is_reported_per_band() {
+        let reports = Arc::new(Mutex::new(vec![]));
+        let job = {
+            let reports = reports.clone();
+            Job::new(move |progress| reports.lock().unwrap().push(progress))
+        };
+        let mut data = vec![0u8; 10 * 40];
+        job.run(|| {
+            par_rows(&mut data, 10, |y, row| row.fill(y as u8));
+            Ok(())
+        })
+        .unwrap();
+
+        let mut reports = reports.lock().unwrap();
+        reports.sort_by_key(|p| p.done);
+        let done: Vec<usize> = reports.iter().map(|p| p.done).collect();
+        assert_eq!(done, [1, 2, 3]);
+        assert!(reports.iter().all(|p| p.step == 1 && p.total == 3));
+        assert_eq!(data[10 * 39], 39);
+    }
+
+    #[test]
+    fn cancelled_jobs_stop_and_fail() {
+        let job = Job::new(|_| {});
+        job.cancel();
+        let mut data = vec![0u8; 10 * 40];
+        let result = job.run(|| {
+            par_rows(&mut data, 10, |_, row| row.fill(1));
+            Ok(())
+        });
+        assert_eq!(result, Err(DipError::Cancelled));
+        assert!(data.iter().all(|&v| v == 0));
+        assert!(current().is_none());
+    }
+}
@@ -0,0 +1,15 @@
+pub mod binary_op;
+pub mod border;
+pub mod color;
+pub mod fft;
+pub mod filter;
+pub mod fourier_desc;
+pub mod geometric;
+pub mod interpolation;
+pub mod job;
+pub mod registry;
+pub mod spectrum;
+
+mod utils;
+
+pub use utils::{set_threads, threads};
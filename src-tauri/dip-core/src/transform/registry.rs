@@ -0,0 +1,668 @@
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::error::DipError;
+use crate::transform::spectrum::Spectrum;
+use crate::transform::{binary_op, color, fft, filter, fourier_desc, geometric};
+
+/// A single argument value, either as sent by the frontend or after validation.
+///
+/// Raw values may be strings even for numeric parameters (text inputs, CLI
+/// arguments); `ParamSpec::parse` converts them to the declared type.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ParamValue {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    /// Rows of numbers, written as `"1 2 1; 0 0 0; -1 -2 -1"` in text.
+    Matrix(Vec<Vec<f64>>),
+}
+
+impl std::fmt::Display for ParamValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamValue::Int(v) => write!(f, "{}", v),
+            ParamValue::Float(v) => write!(f, "{}", v),
+            ParamValue::Str(v) => write!(f, "{}", v),
+            ParamValue::Matrix(rows) => {
+                let rows: Vec<String> = rows
+                    .iter()
+                    .map(|row| {
+                        let row: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+                        row.join(" ")
+                    })
+                    .collect();
+                write!(f, "{}", rows.join("; "))
+            }
+        }
+    }
+}
+
+/// Type and range of a parameter. Unbounded float ranges serialize as `null`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ParamKind {
+    Int {
+        min: i64,
+        max: i64,
+    },
+    Float {
+        min: f64,
+        max: f64,
+    },
+    Choice {
+        options: Vec<&'static str>,
+    },
+    /// A rectangular matrix of any size.
+    Matrix,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    #[serde(flatten)]
+    pub kind: ParamKind,
+    /// `None` marks a required parameter.
+    pub default: Option<ParamValue>,
+}
+
+impl ParamSpec {
+    pub fn int(name: &'static str, min: i64, max: i64, default: Option<i64>) -> Self {
+        ParamSpec {
+            name,
+            kind: ParamKind::Int { min, max },
+            default: default.map(ParamValue::Int),
+        }
+    }
+
+    pub fn float(name: &'static str, min: f64, max: f64, default: Option<f64>) -> Self {
+        ParamSpec {
+            name,
+            kind: ParamKind::Float { min, max },
+            default: default.map(ParamValue::Float),
+        }
+    }
+
+    pub fn choice(name: &'static str, options: &[&'static str], default: Option<&str>) -> Self {
+        ParamSpec {
+            name,
+            kind: ParamKind::Choice {
+                options: options.to_vec(),
+            },
+            default: default.map(|d| ParamValue::Str(d.to_string())),
+        }
+    }
+
+    pub fn matrix(name: &'static str, default: Option<Vec<Vec<f64>>>) -> Self {
+        ParamSpec {
+            name,
+            kind: ParamKind::Matrix,
+            default: default.map(ParamValue::Matrix),
+        }
+    }
+
+    fn parse(&self, raw: &ParamValue) -> Result<ParamValue, DipError> {
+        match &self.kind {
+            ParamKind::Int { min, max } => {
+                let value = match raw {
+                    ParamValue::Int(v) => *v,
+                    ParamValue::Float(v) if v.fract() == 0.0 => *v as i64,
+                    ParamValue::Str(s) => s.trim().parse::<i64>().map_err(|_| {
+                        DipError::InvalidArgument(format!(
+                            "Parameter \"{}\" expects an integer, got \"{}\"",
+                            self.name, s
+                        ))
+                    })?,
+                    _ => {
+                        return Err(DipError::InvalidArgument(format!(
+                            "Parameter \"{}\" expects an integer, got {}",
+                            self.name, raw
+                        )))
+                    }
+                };
+                if value < *min || value > *max {
+                    return Err(DipError::InvalidArgument(format!(
+                        "Parameter \"{}\" must be between {} and {}, got {}",
+                        self.name, min, max, value
+                    )));
+                }
+                Ok(ParamValue::Int(value))
+            }
+            ParamKind::Float { min, max } => {
+                let value = match raw {
+                    ParamValue::Int(v) => *v as f64,
+                    ParamValue::Float(v) => *v,
+                    ParamValue::Str(s) => s.trim().parse::<f64>().map_err(|_| {
+                        DipError::InvalidArgument(format!(
+                            "Parameter \"{}\" expects a number, got \"{}\"",
+                            self.name, s
+                        ))
+                    })?,
+                    ParamValue::Matrix(_) => {
+                        return Err(DipError::InvalidArgument(format!(
+                            "Parameter \"{}\" expects a number, got {}",
+                            self.name, raw
+                        )))
+                    }
+                };
+                if !value.is_finite() || value < *min || value > *max {
+                    return Err(DipError::InvalidArgument(format!(
+                        "Parameter \"{}\" must be between {} and {}, got {}",
+                        self.name, min, max, value
+                    )));
+                }
+                Ok(ParamValue::Float(value))
+            }
+            ParamKind::Choice { options } => {
+                let value = raw.to_string();
+                let value = value.trim();
+                if options.contains(&value) {
+                    Ok(ParamValue::Str(value.to_string()))
+                } else {
+                    Err(DipError::InvalidArgument(format!(
+                        "Parameter \"{}\" must be one of {}, got \"{}\"",
+                        self.name,
+                        options.join(", "),
+                        value
+                    )))
+                }
+            }
+            ParamKind::Matrix => {
+                let rows = match raw {
+                    ParamValue::Matrix(rows) => rows.clone(),
+                    ParamValue::Str(s) => s
+                        .split([';', '\n'])
+                        .map(|row| row.trim())
+                        .filter(|row| !row.is_empty())
+                        .map(|row| {
+                            row.split_whitespace()
+                                .map(|v| v.parse::<f64>())
+                                .collect::<Result<Vec<f64>, _>>()
+                        })
+                        .collect::<Result<Vec<Vec<f64>>, _>>()
+                        .map_err(|_| {
+                            DipError::InvalidArgument(format!(
+                                "Parameter \"{}\" expects rows of numbers separated by \";\", got \"{}\"",
+                                self.name, s
+                            ))
+                        })?,
+                    ParamValue::Int(v) => vec![vec![*v as f64]],
+                    ParamValue::Float(v) => vec![vec![*v]],
+                };
+                let columns = rows.first().map_or(0, |row| row.len());
+                if columns == 0 || rows.iter().any(|row| row.len() != columns) {
+                    return Err(DipError::InvalidArgument(format!(
+                        "Parameter \"{}\" must be a non-empty matrix with rows of equal length, got \"{}\"",
+                        self.name, raw
+                    )));
+                }
+                if rows.iter().flatten().any(|v| !v.is_finite()) {
+                    return Err(DipError::InvalidArgument(format!(
+                        "Parameter \"{}\" must only contain finite numbers",
+                        self.name
+                    )));
+                }
+                Ok(ParamValue::Matrix(rows))
+            }
+        }
+    }
+}
+
+/// Validated arguments of a transform, with defaults filled in.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Args(BTreeMap<String, ParamValue>);
+
+impl Args {
+    /// Validates named raw arguments against `params`.
+    pub fn resolve(
+        params: &[ParamSpec],
+        raw: &BTreeMap<String, ParamValue>,
+    ) -> Result<Args, DipError> {
+        if let Some(unknown) = raw.keys().find(|k| !params.iter().any(|p| p.name == *k)) {
+            return Err(DipError::InvalidArgument(format!(
+                "Unknown parameter \"{}\"",
+                unknown
+            )));
+        }
+        let mut args = BTreeMap::new();
+        for param in params {
+            let value = match (raw.get(param.name), &param.default) {
+                (Some(ParamValue::Str(s)), Some(default)) if s.trim().is_empty() => default.clone(),
+                (Some(raw), _) => param.parse(raw)?,
+                (None, Some(default)) => default.clone(),
+                (None, None) => {
+                    return Err(DipError::InvalidArgument(format!(
+                        "Missing required parameter \"{}\"",
+                        param.name
+                    )))
+                }
+            };
+            args.insert(param.name.to_string(), value);
+        }
+        Ok(Args(args))
+    }
+
+    /// Validates comma-separated positional arguments, e.g. `"5,1.2"`.
+    /// Empty fields take the parameter's default.
+    pub fn from_positional(params: &[ParamSpec], arg: &str) -> Result<Args, DipError> {
+        let values: Vec<&str> = if arg.trim().is_empty() {
+            vec![]
+        } else {
+            arg.split(',').map(|s| s.trim()).collect()
+        };
+        if values.len() > params.len() {
+            return Err(DipError::InvalidArgument(format!(
+                "Expected at most {} arguments, got {}",
+                params.len(),
+                values.len()
+            )));
+        }
+        let raw = params
+            .iter()
+            .zip(values)
+            .map(|(p, v)| (p.name.to_string(), ParamValue::Str(v.to_string())))
+            .collect();
+        Args::resolve(params, &raw)
+    }
+
+    pub fn values(&self) -> &BTreeMap<String, ParamValue> {
+        &self.0
+    }
+
+    pub fn int(&self, name: &str) -> i64 {
+        match self.0.get(name) {
+            Some(ParamValue::Int(v)) => *v,
+            _ => 0,
+        }
+    }
+
+    pub fn float(&self, name: &str) -> f64 {
+        match self.0.get(name) {
+            Some(ParamValue::Float(v)) => *v,
+            Some(ParamValue::Int(v)) => *v as f64,
+            _ => 0.0,
+        }
+    }
+
+    pub fn str(&self, name: &str) -> &str {
+        match self.0.get(name) {
+            Some(ParamValue::Str(v)) => v.as_str(),
+            _ => "",
+        }
+    }
+
+    pub fn matrix(&self, name: &str) -> &[Vec<f64>] {
+        match self.0.get(name) {
+            Some(ParamValue::Matrix(rows)) => rows,
+            _ => &[],
+        }
+    }
+}
+
+/// Numbers a transform found out about its input, such as how far one image
+/// is shifted from the other, by name.
+pub type Measurements = BTreeMap<&'static str, f64>;
+
+/// The result of a transform along with anything it measured.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Output {
+    pub image: DynamicImage,
+    pub measurements: Measurements,
+    /// For results in the frequency domain, the spectrum that `image` is a
+    /// view of. Later transforms that work on spectra take this instead.
+    pub spectrum: Option<Spectrum>,
+}
+
+impl From<DynamicImage> for Output {
+    fn from(image: DynamicImage) -> Self {
+        Output {
+            image,
+            measurements: Measurements::new(),
+            spectrum: None,
+        }
+    }
+}
+
+impl From<Spectrum> for Output {
+    fn from(spectrum: Spectrum) -> Self {
+        Output {
+            image: spectrum.view(),
+            measurements: Measurements::new(),
+            spectrum: Some(spectrum),
+        }
+    }
+}
+
+type UnaryFn = fn(&DynamicImage, &Args) -> Result<DynamicImage, DipError>;
+type UnaryOutputFn = fn(&DynamicImage, &Args) -> Result<Output, DipError>;
+type BinaryFn = fn(&DynamicImage, &DynamicImage, &Args) -> Result<DynamicImage, DipError>;
+type MeasuredBinaryFn = fn(&DynamicImage, &DynamicImage, &Args) -> Result<Output, DipError>;
+type SpectrumFn = fn(&Spectrum, &Args) -> Result<Output, DipError>;
+
+pub trait Transform: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn params(&self) -> &[ParamSpec];
+
+    /// Whether the transform takes a second input image (`img2`).
+    fn needs_second_image(&self) -> bool {
+        false
+    }
+
+    fn apply(
+        &self,
+        img: &DynamicImage,
+        img2: Option<&DynamicImage>,
+        args: &Args,
+    ) -> Result<DynamicImage, DipError>;
+
+    /// Like `apply`, but also returns what the transform measured, which is
+    /// nothing for most of them.
+    fn apply_measured(
+        &self,
+        img: &DynamicImage,
+        img2: Option<&DynamicImage>,
+        args: &Args,
+    ) -> Result<Output, DipError> {
+        Ok(self.apply(img, img2, args)?.into())
+    }
+
+    /// Applies the transform to a spectrum, for transforms that work in the
+    /// frequency domain. `None` for the rest.
+    fn apply_spectrum(
+        &self,
+        _spectrum: &Spectrum,
+        _args: &Args,
+    ) -> Option<Result<Output, DipError>> {
+        None
+    }
+
+    /// Applies the transform to `spectrum` if there is one and the transform
+    /// works on spectra, and to `img`, its view, otherwise. This is how
+    /// pipelines should carry on from an earlier `Output`.
+    fn apply_with_spectrum(
+        &self,
+        img: &DynamicImage,
+        spectrum: Option<&Spectrum>,
+        img2: Option<&DynamicImage>,
+        args: &Args,
+    ) -> Result<Output, DipError> {
+        match spectrum.and_then(|spectrum| self.apply_spectrum(spectrum, args)) {
+            Some(output) => output,
+            None => self.apply_measured(img, img2, args),
+        }
+    }
+}
+
+enum ApplyFn {
+    Unary(UnaryFn),
+    UnaryOutput(UnaryOutputFn),
+    Binary(BinaryFn),
+    MeasuredBinary(MeasuredBinaryFn),
+}
+
+/// A transform backed by a plain function, which covers every built-in one.
+pub struct FnTransform {
+    name: &'static str,
+    params: Vec<ParamSpec>,
+    apply: ApplyFn,
+    spectrum: Option<SpectrumFn>,
+}
+
+impl Transform for FnTransform {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn params(&self) -> &[ParamSpec] {
+        &self.params
+    }
+
+    fn needs_second_image(&self) -> bool {
+        matches!(self.apply, ApplyFn::Binary(_) | ApplyFn::MeasuredBinary(_))
+    }
+
+    fn apply(
+        &self,
+        img: &DynamicImage,
+        img2: Option<&DynamicImage>,
+        args: &Args,
+    ) -> Result<DynamicImage, DipError> {
+        Ok(self.apply_measured(img, img2, args)?.image)
+    }
+
+    fn apply_measured(
+        &self,
+        img: &DynamicImage,
+        img2: Option<&DynamicImage>,
+        args: &Args,
+    ) -> Result<Output, DipError> {
+        match (&self.apply, img2) {
+            (ApplyFn::Unary(f), _) => Ok(f(img, args)?.into()),
+            (ApplyFn::UnaryOutput(f), _) => f(img, args),
+            (ApplyFn::Binary(f), Some(img2)) => Ok(f(img, img2, args)?.into()),
+            (ApplyFn::MeasuredBinary(f), Some(img2)) => f(img, img2, args),
+            (_, None) => Err(DipError::MissingSecondImage(self.name.to_string())),
+        }
+    }
+
+    fn apply_spectrum(&self, spectrum: &Spectrum, args: &Args) -> Option<Result<Output, DipError>> {
+        self.spectrum.map(|f| f(spectrum, args))
+    }
+}
+
+#[derive(Serialize)]
+pub struct TransformInfo {
+    pub name: &'static str,
+    pub params: Vec<ParamSpec>,
+    pub second_image: bool,
+}
+
+pub struct Registry {
+    transforms: Vec<Box<dyn Transform>>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Registry {
+    /// Creates a registry containing every built-in transform.
+    pub fn new() -> Self {
+        let mut registry = Registry { transforms: vec![] };
+        color::register(&mut registry);
+        geometric::register(&mut registry);
+        binary_op::register(&mut registry);
+        filter::register(&mut registry);
+        fft::register(&mut registry);
+        fourier_desc::register(&mut registry);
+        registry
+    }
+
+    pub fn register(&mut self, transform: impl Transform + 'static) {
+        self.transforms.push(Box::new(transform));
+    }
+
+    pub fn add(&mut self, name: &'static str, params: Vec<ParamSpec>, apply: UnaryFn) {
+        self.register(FnTransform {
+            name,
+            params,
+            apply: ApplyFn::Unary(apply),
+            spectrum: None,
+        });
+    }
+
+    pub fn add_binary(&mut self, name: &'static str, params: Vec<ParamSpec>, apply: BinaryFn) {
+        self.register(FnTransform {
+            name,
+            params,
+            apply: ApplyFn::Binary(apply),
+            spectrum: None,
+        });
+    }
+
+    /// Adds a binary transform that also reports measurements.
+    pub fn add_binary_measured(
+        &mut self,
+        name: &'static str,
+        params: Vec<ParamSpec>,
+        apply: MeasuredBinaryFn,
+    ) {
+        self.register(FnTransform {
+            name,
+            params,
+            apply: ApplyFn::MeasuredBinary(apply),
+            spectrum: None,
+        });
+    }
+
+    /// Adds a transform that also works on spectra, with `apply` for images
+    /// and `spectrum` for spectra.
+    pub fn add_spectral(
+        &mut self,
+        name: &'static str,
+        params: Vec<ParamSpec>,
+        apply: UnaryOutputFn,
+        spectrum: SpectrumFn,
+    ) {
+        self.register(FnTransform {
+            name,
+            params,
+            apply: ApplyFn::UnaryOutput(apply),
+            spectrum: Some(spectrum),
+        });
+    }
+
+    pub fn get(&self, name: &str) -> Result<&dyn Transform, DipError> {
+        self.transforms
+            .iter()
+            .find(|t| t.name() == name)
+            .map(|t| t.as_ref())
+            .ok_or_else(|| DipError::UnknownTransform(name.to_string()))
+    }
+
+    /// Looks up `name` and resolves `raw` against its parameters, naming the
+    /// transform in any argument error.
+    pub fn resolve_args(
+        &self,
+        name: &str,
+        raw: &BTreeMap<String, ParamValue>,
+    ) -> Result<(&dyn Transform, Args), DipError> {
+        let transform = self.get(name)?;
+        let args = Args::resolve(transform.params(), raw).map_err(|e| match e {
+            DipError::InvalidArgument(message) => DipError::InvalidArgument(format!(
+                "Invalid arguments for {}: {}",
+                transform.name(),
+                message
+            )),
+            e => e,
+        })?;
+        Ok((transform, args))
+    }
+
+    pub fn list(&self) -> Vec<TransformInfo> {
+        self.transforms
+            .iter()
+            .map(|t| TransformInfo {
+                name: t.name(),
+                params: t.params().to_vec(),
+                second_image: t.needs_second_image(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> Vec<ParamSpec> {
+        vec![
+            ParamSpec::int("size", 1, 9, Some(3)),
+            ParamSpec::float("sigma", 0.0, f64::INFINITY, None),
+            ParamSpec::choice("axis", &["x", "y"], Some("x")),
+        ]
+    }
+
+    #[test]
+    fn resolve_fills_defaults_and_parses_strings() {
+        let raw = BTreeMap::from([("sigma".to_string(), ParamValue::Str("1.5".to_string()))]);
+        let args = Args::resolve(&params(), &raw).unwrap();
+        assert_eq!(args.int("size"), 3);
+        assert_eq!(args.float("sigma"), 1.5);
+        assert_eq!(args.str("axis"), "x");
+    }
+
+    #[test]
+    fn resolve_rejects_bad_arguments() {
+        let cases = [
+            ("sigma", ParamValue::Str("abc".to_string())),
+            ("size", ParamValue::Int(10)),
+            ("size", ParamValue::Float(2.5)),
+            ("axis", ParamValue::Str("z".to_string())),
+            ("unknown", ParamValue::Int(1)),
+        ];
+        for (name, value) in cases {
+            let mut raw = BTreeMap::from([("sigma".to_string(), ParamValue::Float(1.0))]);
+            raw.insert(name.to_string(), value);
+            assert!(matches!(
+                Args::resolve(&params(), &raw),
+                Err(DipError::InvalidArgument(_))
+            ));
+        }
+        assert!(matches!(
+            Args::resolve(&params(), &BTreeMap::new()),
+            Err(DipError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn positional_arguments_follow_param_order() {
+        let args = Args::from_positional(&params(), " , 2, y").unwrap();
+        assert_eq!(args.int("size"), 3);
+        assert_eq!(args.float("sigma"), 2.0);
+        assert_eq!(args.str("axis"), "y");
+        assert!(Args::from_positional(&params(), "3,1,x,4").is_err());
+    }
+
+    #[test]
+    fn matrices_are_parsed_from_rows() {
+        let spec = ParamSpec::matrix("kernel", None);
+        let parsed = spec
+            .parse(&ParamValue::Str(" 1 2.5 ;-1 0\n".to_string()))
+            .unwrap();
+        assert_eq!(
+            parsed,
+            ParamValue::Matrix(vec![vec![1.0, 2.5], vec![-1.0, 0.0]])
+        );
+        assert_eq!(parsed.to_string(), "1 2.5; -1 0");
+        assert_eq!(spec.parse(&parsed).unwrap(), parsed);
+
+        for bad in ["", "1 2; 3", "1 x", "1 inf"] {
+            assert!(
+                spec.parse(&ParamValue::Str(bad.to_string())).is_err(),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_transform_and_missing_second_image() {
+        let registry = Registry::new();
+        assert!(matches!(
+            registry.get("color/nope"),
+            Err(DipError::UnknownTransform(_))
+        ));
+        let img = DynamicImage::new_rgb8(4, 4);
+        let add = registry.get("binary_op/add").unwrap();
+        assert!(matches!(
+            add.apply(&img, None, &Args::default()),
+            Err(DipError::MissingSecondImage(_))
+        ));
+    }
+}
@@ -0,0 +1,273 @@
+use image::{ColorType, DynamicImage};
+use rustfft::{num_complex::Complex, FftPlanner};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::error::DipError;
+use crate::transform::fft::{
+    detect_spikes, dft_2d_complex, filter_gains, notch_gains, shift_to_center_f64, windowed, Band,
+    DftOptions, FrequencyFilter,
+};
+use crate::transform::utils::{channels_f64, from_channels_f64};
+
+type Rows = Vec<Vec<Complex<f64>>>;
+
+/// How a `Spectrum` is shown as an image, with the zero frequency in the
+/// middle and every channel scaled to the full range on its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpectrumView {
+    Magnitude,
+    /// log(1 + |F|), as `fft/dft` shows it.
+    LogMagnitude,
+    /// The angle from -π to π, as black to white.
+    Phase,
+    /// Mid-gray at 0.
+    Real,
+    /// Mid-gray at 0.
+    Imaginary,
+}
+
+impl Default for SpectrumView {
+    fn default() -> Self {
+        SpectrumView::LogMagnitude
+    }
+}
+
+impl SpectrumView {
+    pub const NAMES: [&'static str; 5] =
+        ["magnitude", "log_magnitude", "phase", "real", "imaginary"];
+
+    pub fn from_name(name: &str) -> Option<SpectrumView> {
+        match name {
+            "magnitude" => Some(SpectrumView::Magnitude),
+            "log_magnitude" => Some(SpectrumView::LogMagnitude),
+            "phase" => Some(SpectrumView::Phase),
+            "real" => Some(SpectrumView::Real),
+            "imaginary" => Some(SpectrumView::Imaginary),
+            _ => None,
+        }
+    }
+}
+
+/// The complex spectra of every channel of an image. Unlike a picture of the
+/// spectrum, it keeps the phase, so filters can work on it and `inverse` gives
+/// the image back.
+///
+/// Clones share the spectra, so changing only the view is cheap.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spectrum {
+    /// After padding.
+    width: u32,
+    height: u32,
+    /// Of the image the spectrum was taken from, which `inverse` returns.
+    source_size: (u32, u32),
+    color: ColorType,
+    /// Unshifted, of samples scaled to 0..1.
+    channels: Arc<Vec<Rows>>,
+    view: SpectrumView,
+}
+
+impl Spectrum {
+    pub fn forward(
+        img: &DynamicImage,
+        options: DftOptions,
+        view: SpectrumView,
+    ) -> Result<Spectrum, DipError> {
+        let (width, height) = (img.width(), img.height());
+        let (padded_width, padded_height) = options.padding.padded(width, height)?;
+        let mut planner = FftPlanner::new();
+        let channels = channels_f64(img)
+            .iter()
+            .map(|rows| {
+                let data = windowed(
+                    rows,
+                    options.window,
+                    (padded_width as usize, padded_height as usize),
+                );
+                dft_2d_complex(&data, &mut planner, false)
+            })
+            .collect();
+        Ok(Spectrum {
+            width: padded_width,
+            height: padded_height,
+            source_size: (width, height),
+            color: img.color(),
+            channels: Arc::new(channels),
+            view,
+        })
+    }
+
+    /// After padding.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The same spectrum, shown another way.
+    pub fn with_view(&self, view: SpectrumView) -> Spectrum {
+        Spectrum {
+            view,
+            ..self.clone()
+        }
+    }
+
+    /// The spectrum as an image of the same colour type as the one it was
+    /// taken from.
+    pub fn view(&self) -> DynamicImage {
+        let planes: Vec<Vec<Vec<f64>>> = self
+            .channels
+            .iter()
+            .map(|rows| {
+                let values: Vec<Vec<f64>> = rows
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|value| match self.view {
+                                SpectrumView::Magnitude => value.norm(),
+                                // On the 0..255 scale, like `fft/dft`
+                                SpectrumView::LogMagnitude => (1.0 + 255.0 * value.norm()).ln(),
+                                SpectrumView::Phase => (value.arg() + PI) / (2.0 * PI),
+                                SpectrumView::Real => value.re,
+                                SpectrumView::Imaginary => value.im,
+                            })
+                            .collect()
+                    })
+                    .collect();
+                let peak = values
+                    .iter()
+                    .flatten()
+                    .fold(0.0f64, |peak, value| peak.max(value.abs()));
+                let scale = |value: f64| match self.view {
+                    _ if peak == 0.0 => 0.0,
+                    SpectrumView::Magnitude | SpectrumView::LogMagnitude => value / peak,
+                    SpectrumView::Phase => value,
+                    SpectrumView::Real | SpectrumView::Imaginary => 0.5 + 0.5 * value / peak,
+                };
+                let values: Vec<Vec<f64>> = values
+                    .iter()
+                    .map(|row| row.iter().map(|&value| scale(value)).collect())
+                    .collect();
+                shift_to_center_f64(&values)
+            })
+            .collect();
+        from_channels_f64(self.color, &planes, self.dimensions())
+    }
+
+    /// The image the spectrum was taken from, after any filtering, with the
+    /// padding cut off again. The window is not undone.
+    pub fn inverse(&self) -> DynamicImage {
+        let mut planner = FftPlanner::new();
+        let planes: Vec<Vec<Vec<f64>>> = self
+            .channels
+            .iter()
+            .map(|rows| spatial(rows, &mut planner))
+            .collect();
+        from_channels_f64(self.color, &planes, self.source_size)
+    }
+
+    /// Multiplies every channel by its gains, laid out like the unshifted
+    /// spectrum.
+    fn with_gains(&self, mut gains: impl FnMut(&Rows) -> Vec<Vec<f64>>) -> Spectrum {
+        let channels = self
+            .channels
+            .iter()
+            .map(|rows| {
+                let gains = gains(rows);
+                rows.iter()
+                    .zip(&gains)
+                    .map(|(row, gains)| row.iter().zip(gains).map(|(v, g)| v * g).collect())
+                    .collect()
+            })
+            .collect();
+        Spectrum {
+            channels: Arc::new(channels),
+            ..self.clone()
+        }
+    }
+
+    /// See `fft::frequency_filter`.
+    pub fn filter(&self, filter: FrequencyFilter, band: Band, cutoff: f64, width: f64) -> Spectrum {
+        let gains = filter_gains(self.width, self.height, filter, band, cutoff, width);
+        self.with_gains(|_| gains.clone())
+    }
+
+    /// See `fft::notch`. Spikes are detected in every channel on its own.
+    pub fn notch(
+        &self,
+        points: &[(f64, f64)],
+        detect: Option<f64>,
+        radius: f64,
+        filter: FrequencyFilter,
+    ) -> Spectrum {
+        let mut planner = FftPlanner::new();
+        self.with_gains(|rows| {
+            let mut points = points.to_vec();
+            if let Some(threshold) = detect {
+                let samples: Vec<Vec<f64>> = spatial(rows, &mut planner)
+                    .iter()
+                    .map(|row| row.iter().map(|v| v * 255.0).collect())
+                    .collect();
+                points.extend(detect_spikes(&samples, radius, threshold));
+            }
+            notch_gains(self.width, self.height, &points, radius, filter)
+        })
+    }
+}
+
+/// The real part of the inverse transform of one channel.
+fn spatial(rows: &Rows, planner: &mut FftPlanner<f64>) -> Vec<Vec<f64>> {
+    dft_2d_complex(rows, planner, true)
+        .iter()
+        .map(|row| row.iter().map(|value| value.re).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma, Rgb, RgbImage};
+
+    #[test]
+    fn the_inverse_gives_the_image_back() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_fn(13, 10, |x, y| {
+            Rgb([(x * 19) as u8, (y * 25) as u8, 77])
+        }));
+        for img in [img.clone(), DynamicImage::ImageRgba16(img.to_rgba16())] {
+            let spectrum =
+                Spectrum::forward(&img, DftOptions::default(), SpectrumView::Phase).unwrap();
+            assert_eq!(spectrum.inverse(), img, "{:?}", img.color());
+        }
+
+        let img = DynamicImage::ImageRgb32F(img.to_rgb32f());
+        let inverse = Spectrum::forward(&img, DftOptions::default(), SpectrumView::Phase)
+            .unwrap()
+            .inverse();
+        assert_eq!(inverse.color(), img.color());
+        let inverse = inverse.to_rgb32f();
+        for (a, b) in inverse.as_raw().iter().zip(img.to_rgb32f().as_raw()) {
+            assert!((a - b).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn views_keep_the_colour_type_and_centre_the_zero_frequency() {
+        let img = DynamicImage::ImageLuma8(GrayImage::from_fn(8, 6, |x, y| {
+            Luma([(x * 20 + y * 3) as u8])
+        }));
+        let spectrum =
+            Spectrum::forward(&img, DftOptions::default(), SpectrumView::LogMagnitude).unwrap();
+        let view = spectrum.view().to_luma8();
+        assert_eq!(view.dimensions(), (8, 6));
+        assert_eq!(view.get_pixel(4, 3), &Luma([255]));
+
+        // A real image has an even real part and an odd imaginary part
+        let real = spectrum.with_view(SpectrumView::Real).view().to_luma8();
+        let imaginary = spectrum
+            .with_view(SpectrumView::Imaginary)
+            .view()
+            .to_luma8();
+        assert_eq!(real.get_pixel(5, 3), real.get_pixel(3, 3));
+        assert_eq!(imaginary.get_pixel(4, 3), &Luma([128]));
+        let sum = imaginary.get_pixel(5, 3).0[0] as i32 + imaginary.get_pixel(3, 3).0[0] as i32;
+        assert!((sum - 256).abs() <= 1, "{}", sum);
+    }
+}
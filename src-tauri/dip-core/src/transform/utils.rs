@@ -0,0 +1,510 @@
+use image::{
+    ColorType, DynamicImage, GenericImageView, ImageBuffer, Luma, LumaA, Pixel, Primitive, Rgb,
+    Rgba,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::transform::border::BorderMode;
+use crate::transform::job;
+
+/// Subpixel types that transforms work on without quantising to 8 bits.
+pub trait Sample: Primitive + Send + Sync + 'static {
+    /// `Luma<Self>`. Generic code can't name its `Pixel` impl directly, as
+    /// that depends on a trait `image` doesn't export.
+    type GrayPixel: Pixel<Subpixel = Self> + Send + Sync + 'static;
+    /// `Rgb<Self>`
+    type RgbPixel: Pixel<Subpixel = Self> + Send + Sync + 'static;
+
+    /// The value of white: 255 for `u8`, 65535 for `u16` and 1.0 for `f32`.
+    const MAX: f64;
+
+    fn into_f64(self) -> f64;
+
+    /// Clamps to `0..=MAX` and converts like `as` does, truncating integers.
+    fn from_f64(value: f64) -> Self;
+
+    /// Like `from_f64`, but rounds integers to the nearest value.
+    fn round_from_f64(value: f64) -> Self;
+
+    /// Number of distinct values, for types that are cheap to histogram.
+    const LEVELS: Option<usize>;
+
+    /// Histogram bin of the value. Only meaningful if `LEVELS` is `Some`.
+    fn level(self) -> usize;
+}
+
+macro_rules! impl_integer_sample {
+    ($t:ty, $levels:expr) => {
+        impl Sample for $t {
+            type GrayPixel = Luma<$t>;
+            type RgbPixel = Rgb<$t>;
+
+            const MAX: f64 = <$t>::MAX as f64;
+
+            fn into_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn from_f64(value: f64) -> Self {
+                value.clamp(0.0, <Self as Sample>::MAX) as $t
+            }
+
+            fn round_from_f64(value: f64) -> Self {
+                Self::from_f64(value.round())
+            }
+
+            const LEVELS: Option<usize> = $levels;
+
+            fn level(self) -> usize {
+                self as usize
+            }
+        }
+    };
+}
+
+impl_integer_sample!(u8, Some(256));
+// Walking 65536 histogram bins costs more than it saves
+impl_integer_sample!(u16, None);
+
+impl Sample for f32 {
+    type GrayPixel = Luma<f32>;
+    type RgbPixel = Rgb<f32>;
+
+    const MAX: f64 = 1.0;
+
+    fn into_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value.clamp(0.0, 1.0) as f32
+    }
+
+    fn round_from_f64(value: f64) -> Self {
+        Self::from_f64(value)
+    }
+
+    const LEVELS: Option<usize> = None;
+
+    fn level(self) -> usize {
+        0
+    }
+}
+
+/// A single channel at any bit depth.
+pub type Plane<T> = ImageBuffer<<T as Sample>::GrayPixel, Vec<T>>;
+
+pub type RgbBuffer<T> = ImageBuffer<<T as Sample>::RgbPixel, Vec<T>>;
+
+pub fn gray<T: Sample>(value: T) -> T::GrayPixel {
+    *T::GrayPixel::from_slice(&[value])
+}
+
+pub fn rgb<T: Sample>(values: [T; 3]) -> T::RgbPixel {
+    *T::RgbPixel::from_slice(&values)
+}
+
+/// Sample type of a `DynamicImage`, ordered by precision.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Depth {
+    U8,
+    U16,
+    F32,
+}
+
+impl Depth {
+    pub fn of(img: &DynamicImage) -> Depth {
+        match img.color() {
+            ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16 => Depth::U16,
+            ColorType::Rgb32F | ColorType::Rgba32F => Depth::F32,
+            _ => Depth::U8,
+        }
+    }
+}
+
+/// Wraps a float gray result. `DynamicImage` has no 32-bit float gray variant,
+/// so it becomes an `Rgb32F` image with three equal channels.
+pub fn gray_to_rgb32f(gray: Plane<f32>) -> DynamicImage {
+    let (width, height) = gray.dimensions();
+    let rgb = RgbBuffer::from_fn(width, height, |x, y| {
+        let value = gray.get_pixel(x, y).0[0];
+        Rgb([value; 3])
+    });
+    DynamicImage::ImageRgb32F(rgb)
+}
+
+/// Runs `$body` on the gray plane of `$img` at the image's own bit depth,
+/// bound to `$gray`, and wraps the resulting plane in a `DynamicImage`.
+macro_rules! map_gray {
+    ($img:expr, |$gray:ident| $body:expr) => {{
+        let img: &image::DynamicImage = $img;
+        match $crate::transform::utils::Depth::of(img) {
+            $crate::transform::utils::Depth::U8 => {
+                let $gray = &img.to_luma8();
+                image::DynamicImage::ImageLuma8($body)
+            }
+            $crate::transform::utils::Depth::U16 => {
+                let $gray = &img.to_luma16();
+                image::DynamicImage::ImageLuma16($body)
+            }
+            $crate::transform::utils::Depth::F32 => {
+                let $gray = &img.to_luma32f();
+                $crate::transform::utils::gray_to_rgb32f($body)
+            }
+        }
+    }};
+}
+pub(crate) use map_gray;
+
+/// Runs `$body` on the buffer inside `$img`, bound to `$buf`, and wraps the
+/// resulting buffer in the same `DynamicImage` variant.
+macro_rules! map_buffer {
+    ($img:expr, |$buf:ident| $body:expr) => {{
+        use image::DynamicImage::*;
+        let img: &image::DynamicImage = $img;
+        match img {
+            ImageLuma8($buf) => ImageLuma8($body),
+            ImageLumaA8($buf) => ImageLumaA8($body),
+            ImageRgb8($buf) => ImageRgb8($body),
+            ImageRgba8($buf) => ImageRgba8($body),
+            ImageLuma16($buf) => ImageLuma16($body),
+            ImageLumaA16($buf) => ImageLumaA16($body),
+            ImageRgb16($buf) => ImageRgb16($body),
+            ImageRgba16($buf) => ImageRgba16($body),
+            ImageRgb32F($buf) => ImageRgb32F($body),
+            ImageRgba32F($buf) => ImageRgba32F($body),
+            img => {
+                let $buf = &img.to_rgba32f();
+                ImageRgba32F($body)
+            }
+        }
+    }};
+}
+pub(crate) use map_buffer;
+
+static THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Rows per unit of work handed to a thread by `par_bands` and `par_rows`.
+const BAND_ROWS: usize = 16;
+
+/// Sets how many threads transforms may use. 0, the default, uses one per
+/// core and 1 runs everything on the calling thread. The work runs on rayon's
+/// global pool, so more threads than it has are clamped to its size.
+pub fn set_threads(threads: usize) {
+    THREADS.store(threads.min(rayon::current_num_threads()), Ordering::Relaxed);
+}
+
+/// The number of threads transforms currently use.
+pub fn threads() -> usize {
+    match THREADS.load(Ordering::Relaxed) {
+        0 => rayon::current_num_threads(),
+        threads => threads,
+    }
+}
+
+/// Runs `f` on every task, spread over `threads()` threads.
+fn spread<T: Send>(tasks: Vec<T>, f: &(impl Fn(T) + Sync)) {
+    let threads = threads().min(tasks.len());
+    if threads <= 1 {
+        tasks.into_iter().for_each(f);
+        return;
+    }
+    let mut groups: Vec<Vec<T>> = (0..threads).map(|_| vec![]).collect();
+    for (i, task) in tasks.into_iter().enumerate() {
+        groups[i % threads].push(task);
+    }
+    rayon::scope(|scope| {
+        for group in groups {
+            scope.spawn(move |_| group.into_iter().for_each(f));
+        }
+    });
+}
+
+/// Calls `f` with the index of the first row and the samples of every band
+/// of up to `BAND_ROWS` rows of `data`, in parallel. The bands don't depend on
+/// the thread count, so neither does the result.
+///
+/// Each band counts towards the progress of the current job, and once that
+/// is cancelled the bands left are skipped.
+pub fn par_bands<S: Send>(data: &mut [S], row_len: usize, f: impl Fn(usize, &mut [S]) + Sync) {
+    if data.is_empty() || row_len == 0 {
+        return;
+    }
+    let bands: Vec<_> = data
+        .chunks_mut(row_len * BAND_ROWS)
+        .enumerate()
+        .map(|(i, band)| (i * BAND_ROWS, band))
+        .collect();
+    let job = job::current();
+    if let Some(job) = &job {
+        job.start_step(bands.len());
+    }
+    spread(bands, &|(first_row, band)| match &job {
+        Some(job) if job.is_cancelled() => {}
+        Some(job) => {
+            f(first_row, band);
+            job.band_done();
+        }
+        None => f(first_row, band),
+    });
+}
+
+/// Calls `f` with the index and the samples of every row of `data`, in
+/// parallel.
+pub fn par_rows<S: Send>(data: &mut [S], row_len: usize, f: impl Fn(usize, &mut [S]) + Sync) {
+    par_bands(data, row_len, |first_row, band| {
+        for (i, row) in band.chunks_mut(row_len).enumerate() {
+            f(first_row + i, row);
+        }
+    });
+}
+
+/// Calls `f` with the index of every item, in parallel.
+pub fn par_for_each<T: Send>(items: &mut [T], f: impl Fn(usize, &mut T) + Sync) {
+    par_rows(items, 1, |i, item| f(i, &mut item[0]));
+}
+
+pub fn max_dimensions<I>(img1: &I, img2: &I) -> (u32, u32)
+where
+    I: GenericImageView,
+{
+    let width = img1.width().max(img2.width());
+    let height = img1.height().max(img2.height());
+    (width, height)
+}
+
+pub fn get_pixel_rgb<T: Sample>(img: &RgbBuffer<T>, x: u32, y: u32) -> T::RgbPixel {
+    if x < img.width() && y < img.height() {
+        *img.get_pixel(x, y)
+    } else {
+        rgb([T::DEFAULT_MIN_VALUE; 3])
+    }
+}
+
+/// The sample at `(x, y)`, with pixels outside of `img` made up by `border`.
+pub fn get_pixel_grayscale<T: Sample>(img: &Plane<T>, x: i32, y: i32, border: BorderMode) -> T {
+    match border.locate(x as i64, y as i64, img.dimensions()) {
+        Some((x, y)) => img.get_pixel(x, y).channels()[0],
+        None => T::DEFAULT_MIN_VALUE,
+    }
+}
+
+/// Correlates `img` with `kernel`, whose rows run along y and whose element
+/// at `anchor` (column, row) lies over the output pixel. `bias` is added to
+/// every sum before it is clamped.
+pub fn apply_kernel_f64_gray<T: Sample>(
+    img: &Plane<T>,
+    kernel: &[Vec<f64>],
+    (anchor_x, anchor_y): (usize, usize),
+    bias: f64,
+    border: BorderMode,
+) -> Plane<T> {
+    let (width, height) = img.dimensions();
+    let mut result = Plane::new(width, height);
+
+    par_rows(&mut result, width as usize, |y, row| {
+        for (x, value) in row.iter_mut().enumerate() {
+            let mut sum = bias;
+            for (j, kernel_row) in kernel.iter().enumerate() {
+                for (i, weight) in kernel_row.iter().enumerate() {
+                    let px = x as i32 + i as i32 - anchor_x as i32;
+                    let py = y as i32 + j as i32 - anchor_y as i32;
+                    let val = get_pixel_grayscale(img, px, py, border).into_f64();
+                    sum += val * weight;
+                }
+            }
+            *value = T::from_f64(sum);
+        }
+    });
+    result
+}
+
+/// Applies one of the built-in integer kernels, which are indexed `[dx][dy]`
+/// and anchored at their centre.
+pub fn apply_kernel_i32_gray<T: Sample>(
+    img: &Plane<T>,
+    kernel: &[Vec<i32>],
+    border: BorderMode,
+) -> Plane<T> {
+    let k = kernel.len() / 2;
+    let rows: Vec<Vec<f64>> = (0..kernel[0].len())
+        .map(|j| kernel.iter().map(|column| column[j] as f64).collect())
+        .collect();
+    apply_kernel_f64_gray(img, &rows, (k, k), 0.0, border)
+}
+
+pub fn split_rgb<T: Sample>(img: &RgbBuffer<T>) -> [Plane<T>; 3] {
+    let (width, height) = img.dimensions();
+    let mut planes = [
+        Plane::new(width, height),
+        Plane::new(width, height),
+        Plane::new(width, height),
+    ];
+    for (x, y, pixel) in img.enumerate_pixels() {
+        for (plane, &value) in planes.iter_mut().zip(pixel.channels()) {
+            plane.put_pixel(x, y, gray(value));
+        }
+    }
+    planes
+}
+
+pub fn merge_rgb<T: Sample>(planes: &[Plane<T>; 3]) -> RgbBuffer<T> {
+    let [r, g, b] = planes;
+    RgbBuffer::from_fn(r.width(), r.height(), |x, y| {
+        rgb([
+            r.get_pixel(x, y).channels()[0],
+            g.get_pixel(x, y).channels()[0],
+            b.get_pixel(x, y).channels()[0],
+        ])
+    })
+}
+
+/// Every channel of `img`, alpha included, as rows of samples from 0 to 1.
+pub fn channels_f64(img: &DynamicImage) -> Vec<Vec<Vec<f64>>> {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let samples = match img.color().channel_count() {
+        1 => img.to_luma32f().into_raw(),
+        2 => img.to_luma_alpha32f().into_raw(),
+        3 => img.to_rgb32f().into_raw(),
+        _ => img.to_rgba32f().into_raw(),
+    };
+    let count = samples.len() / (width * height).max(1);
+    (0..count)
+        .map(|channel| {
+            samples
+                .chunks(width * count)
+                .map(|row| {
+                    row.iter()
+                        .skip(channel)
+                        .step_by(count)
+                        .map(|&v| v as f64)
+                        .collect()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The top left `width` × `height` of `channels`, samples from 0 to 1 as
+/// `channels_f64` returns them, as an image of colour type `color`.
+pub fn from_channels_f64(
+    color: ColorType,
+    channels: &[Vec<Vec<f64>>],
+    (width, height): (u32, u32),
+) -> DynamicImage {
+    let samples: Vec<f64> = (0..height as usize)
+        .flat_map(|y| {
+            (0..width as usize).flat_map(move |x| channels.iter().map(move |rows| rows[y][x]))
+        })
+        .collect();
+    match color {
+        ColorType::L8 => DynamicImage::ImageLuma8(buffer::<Luma<u8>>(width, height, &samples)),
+        ColorType::La8 => DynamicImage::ImageLumaA8(buffer::<LumaA<u8>>(width, height, &samples)),
+        ColorType::Rgb8 => DynamicImage::ImageRgb8(buffer::<Rgb<u8>>(width, height, &samples)),
+        ColorType::Rgba8 => DynamicImage::ImageRgba8(buffer::<Rgba<u8>>(width, height, &samples)),
+        ColorType::L16 => DynamicImage::ImageLuma16(buffer::<Luma<u16>>(width, height, &samples)),
+        ColorType::La16 => {
+            DynamicImage::ImageLumaA16(buffer::<LumaA<u16>>(width, height, &samples))
+        }
+        ColorType::Rgb16 => DynamicImage::ImageRgb16(buffer::<Rgb<u16>>(width, height, &samples)),
+        ColorType::Rgba16 => {
+            DynamicImage::ImageRgba16(buffer::<Rgba<u16>>(width, height, &samples))
+        }
+        ColorType::Rgb32F => DynamicImage::ImageRgb32F(buffer::<Rgb<f32>>(width, height, &samples)),
+        _ => DynamicImage::ImageRgba32F(buffer::<Rgba<f32>>(width, height, &samples)),
+    }
+}
+
+fn buffer<P>(width: u32, height: u32, samples: &[f64]) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel,
+    P::Subpixel: Sample,
+{
+    let raw = samples
+        .iter()
+        .map(|&value| Sample::round_from_f64(value * <P::Subpixel as Sample>::MAX))
+        .collect();
+    ImageBuffer::from_raw(width, height, raw).unwrap()
+}
+
+// The colour space conversions below work on RGB normalised to 0..1, and put
+// the luminance in 0..1 as well. Results are not clamped.
+
+/// Full-range BT.601 YCbCr, as used by JPEG, with chroma centred on 0.5.
+pub fn rgb_to_ycbcr([r, g, b]: [f32; 3]) -> [f32; 3] {
+    [
+        0.299 * r + 0.587 * g + 0.114 * b,
+        0.5 - 0.168736 * r - 0.331264 * g + 0.5 * b,
+        0.5 + 0.5 * r - 0.418688 * g - 0.081312 * b,
+    ]
+}
+
+pub fn ycbcr_to_rgb([y, cb, cr]: [f32; 3]) -> [f32; 3] {
+    let (cb, cr) = (cb - 0.5, cr - 0.5);
+    [
+        y + 1.402 * cr,
+        y - 0.344136 * cb - 0.714136 * cr,
+        y + 1.772 * cb,
+    ]
+}
+
+// D65 white point
+const WHITE: [f32; 3] = [0.95047, 1.0, 1.08883];
+
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        12.92 * v
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// CIELAB with L* scaled from 0..100 to 0..1.
+pub fn rgb_to_lab(rgb: [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(srgb_to_linear);
+    let xyz = [
+        0.4124 * r + 0.3576 * g + 0.1805 * b,
+        0.2126 * r + 0.7152 * g + 0.0722 * b,
+        0.0193 * r + 0.1192 * g + 0.9505 * b,
+    ];
+    let f = |t: f32| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let [fx, fy, fz] = [0, 1, 2].map(|i| f(xyz[i] / WHITE[i]));
+    [
+        (116.0 * fy - 16.0) / 100.0,
+        500.0 * (fx - fy),
+        200.0 * (fy - fz),
+    ]
+}
+
+pub fn lab_to_rgb([l, a, b]: [f32; 3]) -> [f32; 3] {
+    let fy = (l * 100.0 + 16.0) / 116.0;
+    let f = [fy + a / 500.0, fy, fy - b / 200.0];
+    let f_inv = |t: f32| {
+        if t > 6.0 / 29.0 {
+            t * t * t
+        } else {
+            (116.0 * t - 16.0) * 27.0 / 24389.0
+        }
+    };
+    let [x, y, z] = [0, 1, 2].map(|i| f_inv(f[i]) * WHITE[i]);
+    [
+        3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+        0.0557 * x - 0.2040 * y + 1.0570 * z,
+    ]
+    .map(linear_to_srgb)
+}
@@ -0,0 +1,605 @@
+use dip_core::io::{read_working_image, write_image};
+use dip_core::transform::registry::{Args, Output, ParamKind, ParamSpec, ParamValue, Registry};
+use dip_core::transform::{set_threads, threads};
+use dip_core::DipError;
+use image::{DynamicImage, GenericImageView, ImageBuffer, Rgb, RgbImage};
+use std::collections::BTreeMap;
+
+/// A dark image with a bright square, so contour-based transforms have
+/// something to find.
+fn test_image(width: u32, height: u32) -> DynamicImage {
+    let img = RgbImage::from_fn(width, height, |x, y| {
+        let inside =
+            (width / 4..width * 3 / 4).contains(&x) && (height / 4..height * 3 / 4).contains(&y);
+        if inside {
+            Rgb([200, 220, 240])
+        } else {
+            Rgb([(x * 3) as u8, (y * 5) as u8, 20])
+        }
+    });
+    DynamicImage::ImageRgb8(img)
+}
+
+fn bytes_per_sample(img: &DynamicImage) -> u8 {
+    img.color().bytes_per_pixel() / img.color().channel_count()
+}
+
+fn minimal_args(params: &[ParamSpec]) -> Args {
+    let raw: BTreeMap<String, ParamValue> = params
+        .iter()
+        .filter(|p| p.default.is_none())
+        .map(|p| {
+            let value = match &p.kind {
+                ParamKind::Int { min, .. } => ParamValue::Int((*min).max(8)),
+                ParamKind::Float { min, .. } => ParamValue::Float(*min),
+                ParamKind::Choice { options } => ParamValue::Str(options[0].to_string()),
+                ParamKind::Matrix if p.name == "kernel" => {
+                    ParamValue::Str("0 1 0; 1 -4 1; 0 1 0".to_string())
+                }
+                ParamKind::Matrix => ParamValue::Str("1 0.2 0; 0.1 1 0; 0 0 1".to_string()),
+            };
+            (p.name.to_string(), value)
+        })
+        .collect();
+    Args::resolve(params, &raw).unwrap()
+}
+
+#[test]
+fn every_transform_runs_with_minimal_arguments() {
+    let registry = Registry::new();
+    let img = test_image(64, 48);
+    let inputs = [
+        img.clone(),
+        DynamicImage::ImageRgb16(img.to_rgb16()),
+        DynamicImage::ImageRgb32F(img.to_rgb32f()),
+    ];
+
+    for info in registry.list() {
+        let transform = registry.get(info.name).unwrap();
+        let args = minimal_args(transform.params());
+        for img in &inputs {
+            let img2 = img.fliph();
+            let result = transform.apply(img, Some(&img2), &args);
+            assert!(result.is_ok(), "{} failed: {:?}", info.name, result.err());
+            // Contours are traced on 8-bit images only
+            if info.name != "fourier_desc" {
+                assert_eq!(
+                    bytes_per_sample(&result.unwrap()),
+                    bytes_per_sample(img),
+                    "{} changed the bit depth of {:?}",
+                    info.name,
+                    img.color()
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn threads_do_not_change_results() {
+    let registry = Registry::new();
+    // Tall enough to be split into several bands of rows
+    let img = test_image(70, 90);
+    let inputs = [img.clone(), DynamicImage::ImageRgb32F(img.to_rgb32f())];
+
+    for info in registry.list() {
+        let transform = registry.get(info.name).unwrap();
+        let args = minimal_args(transform.params());
+        for img in &inputs {
+            let img2 = img.fliph();
+            set_threads(1);
+            let serial = transform.apply(img, Some(&img2), &args);
+            set_threads(5);
+            let parallel = transform.apply(img, Some(&img2), &args);
+            assert_eq!(serial, parallel, "{} on {:?}", info.name, img.color());
+        }
+    }
+    set_threads(0);
+    let all = threads();
+    // No more than the pool the work runs on
+    set_threads(all + 1);
+    assert_eq!(threads(), all);
+    set_threads(0);
+}
+
+#[test]
+fn pipeline_matches_individual_steps() {
+    let registry = Registry::new();
+    let img = test_image(16, 16);
+
+    let mirror = registry.get("geometric/mirror").unwrap();
+    let args = Args::from_positional(mirror.params(), "x").unwrap();
+    let once = mirror.apply(&img, None, &args).unwrap();
+    let twice = mirror.apply(&once, None, &args).unwrap();
+    assert_ne!(once.to_rgb8(), img.to_rgb8());
+    assert_eq!(twice.to_rgb8(), img.to_rgb8());
+
+    let resize = registry.get("geometric/resize").unwrap();
+    let args = Args::from_positional(resize.params(), "7,5").unwrap();
+    assert_eq!(
+        resize.apply(&img, None, &args).unwrap().dimensions(),
+        (7, 5)
+    );
+}
+
+#[test]
+fn translate_fills_uncovered_pixels_from_the_border() {
+    let registry = Registry::new();
+    let img = test_image(16, 12);
+    let translate = registry.get("geometric/translate").unwrap();
+
+    let args = Args::from_positional(translate.params(), "16,-12,wrap").unwrap();
+    assert_eq!(translate.apply(&img, None, &args).unwrap(), img);
+
+    let args = Args::from_positional(translate.params(), "3,0,constant").unwrap();
+    let shifted = translate.apply(&img, None, &args).unwrap().to_rgb8();
+    assert_eq!(shifted.get_pixel(2, 5), &Rgb([0, 0, 0]));
+    assert_eq!(shifted.get_pixel(3, 5), img.to_rgb8().get_pixel(0, 5));
+
+    // Not black by default
+    let args = Args::from_positional(translate.params(), "3,0").unwrap();
+    let shifted = translate.apply(&img, None, &args).unwrap().to_rgb8();
+    assert_eq!(shifted.get_pixel(2, 5), img.to_rgb8().get_pixel(1, 5));
+}
+
+#[test]
+fn downscaling_averages_instead_of_picking_pixels() {
+    let registry = Registry::new();
+    let stripes = RgbImage::from_fn(32, 32, |x, _| Rgb([if x % 2 == 0 { 0 } else { 200 }; 3]));
+    let img = DynamicImage::ImageRgb8(stripes);
+    let resize = registry.get("geometric/resize").unwrap();
+
+    let args = Args::from_positional(resize.params(), "8,8,nearest").unwrap();
+    let nearest = resize.apply(&img, None, &args).unwrap().to_rgb8();
+    assert!(nearest.pixels().all(|p| p.0[0] == 0 || p.0[0] == 200));
+
+    for interpolation in ["bilinear", "bicubic", "lanczos3"] {
+        let arg = format!("8,8,{}", interpolation);
+        let args = Args::from_positional(resize.params(), &arg).unwrap();
+        let smooth = resize.apply(&img, None, &args).unwrap().to_rgb8();
+        for p in smooth.pixels() {
+            assert!(
+                (90..=110).contains(&p.0[0]),
+                "{} gave {:?}",
+                interpolation,
+                p
+            );
+        }
+    }
+}
+
+#[test]
+fn quarter_turns_are_lossless() {
+    let registry = Registry::new();
+    let img = test_image(16, 11);
+    let rotate = registry.get("geometric/rotate").unwrap();
+    let turned = |angle: &str, interpolation: &str| {
+        let arg = format!("{},constant,{},expand", angle, interpolation);
+        let args = Args::from_positional(rotate.params(), &arg).unwrap();
+        rotate.apply(&img, None, &args).unwrap()
+    };
+
+    for interpolation in ["nearest", "bilinear", "bicubic", "lanczos3"] {
+        assert_eq!(turned("90", interpolation), img.rotate90());
+        assert_eq!(turned("180", interpolation), img.rotate180());
+        assert_eq!(turned("-90", interpolation), img.rotate270());
+        assert_eq!(turned("360", interpolation), img);
+    }
+}
+
+#[test]
+fn expanded_rotation_keeps_the_corners() {
+    let registry = Registry::new();
+    let img = DynamicImage::ImageRgb8(RgbImage::from_pixel(20, 10, Rgb([255, 255, 255])));
+    let rotate = registry.get("geometric/rotate").unwrap();
+
+    let args = Args::from_positional(rotate.params(), "30,constant,bilinear,expand").unwrap();
+    let rotated = rotate.apply(&img, None, &args).unwrap().to_rgb8();
+    // 20 cos 30° + 10 sin 30° by 20 sin 30° + 10 cos 30°
+    assert_eq!(rotated.dimensions(), (23, 19));
+    let white = rotated.pixels().filter(|p| p.0[0] == 255).count();
+    assert!(white > 150, "only {} white pixels", white);
+    assert_eq!(rotated.get_pixel(0, 0), &Rgb([0, 0, 0]));
+
+    let args = Args::from_positional(rotate.params(), "30,constant,bilinear,keep").unwrap();
+    let cropped = rotate.apply(&img, None, &args).unwrap().to_rgb8();
+    assert_eq!(cropped.dimensions(), (20, 10));
+}
+
+#[test]
+fn rotation_fills_with_the_given_colour() {
+    let registry = Registry::new();
+    let img = test_image(12, 12);
+    let rotate = registry.get("geometric/rotate").unwrap();
+
+    let args =
+        Args::from_positional(rotate.params(), "45,constant,nearest,keep,,,10 20 30").unwrap();
+    let rotated = rotate.apply(&img, None, &args).unwrap();
+    assert_eq!(rotated.color(), img.color());
+    assert_eq!(rotated.to_rgb8().get_pixel(0, 0), &Rgb([10, 20, 30]));
+
+    let args =
+        Args::from_positional(rotate.params(), "45,constant,nearest,keep,,,0 0 0 0").unwrap();
+    let rotated = rotate.apply(&img, None, &args).unwrap().to_rgba8();
+    assert_eq!(rotated.get_pixel(0, 0).0[3], 0);
+    assert_eq!(rotated.get_pixel(6, 6).0[3], 255);
+
+    // About the top left corner, which stays put
+    let args = Args::from_positional(rotate.params(), "90,constant,nearest,keep,0,0").unwrap();
+    let rotated = rotate.apply(&img, None, &args).unwrap().to_rgb8();
+    assert_eq!(rotated.get_pixel(0, 0), img.to_rgb8().get_pixel(0, 0));
+    assert_eq!(rotated.get_pixel(0, 1), img.to_rgb8().get_pixel(1, 0));
+    assert_eq!(rotated.get_pixel(1, 0), &Rgb([0, 0, 0]));
+
+    let args = Args::from_positional(rotate.params(), "10,,,,,,1 2").unwrap();
+    assert!(rotate.apply(&img, None, &args).is_err());
+}
+
+#[test]
+fn warps_move_pixels_where_the_matrix_says() {
+    let registry = Registry::new();
+    let img = test_image(16, 12);
+    let affine = registry.get("geometric/affine").unwrap();
+    let perspective = registry.get("geometric/perspective").unwrap();
+
+    let args = Args::from_positional(affine.params(), "1 0 0; 0 1 0").unwrap();
+    assert_eq!(affine.apply(&img, None, &args).unwrap(), img);
+    let args =
+        Args::from_positional(affine.params(), "1 0 3; 0 1 0; 0 0 1,nearest,constant").unwrap();
+    let translate = registry.get("geometric/translate").unwrap();
+    let translate_args = Args::from_positional(translate.params(), "3,0,constant").unwrap();
+    assert_eq!(
+        affine.apply(&img, None, &args).unwrap(),
+        translate.apply(&img, None, &translate_args).unwrap()
+    );
+
+    // The same shift as four point correspondences
+    let points = "0 0 3 0; 10 0 13 0; 10 10 13 10; 0 10 3 10,nearest,constant";
+    let args = Args::from_positional(perspective.params(), points).unwrap();
+    assert_eq!(
+        perspective.apply(&img, None, &args).unwrap(),
+        translate.apply(&img, None, &translate_args).unwrap()
+    );
+
+    for bad in ["1 0 0; 0 1 0; 1 0 1", "1 2 0; 2 4 0", "1 0; 0 1"] {
+        let args = Args::from_positional(affine.params(), bad).unwrap();
+        assert!(affine.apply(&img, None, &args).is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn crop_pad_and_canvas_keep_the_pixels_in_place() {
+    let registry = Registry::new();
+    let img = test_image(16, 12);
+    let inputs = [
+        img.clone(),
+        DynamicImage::ImageLumaA16(img.to_luma_alpha16()),
+        DynamicImage::ImageRgba32F(img.to_rgba32f()),
+    ];
+    let crop = registry.get("geometric/crop").unwrap();
+    let pad = registry.get("geometric/pad").unwrap();
+    let canvas = registry.get("geometric/canvas").unwrap();
+
+    for img in &inputs {
+        let args = Args::from_positional(crop.params(), "3,2,10,9").unwrap();
+        let cropped = crop.apply(img, None, &args).unwrap();
+        assert_eq!(cropped.color(), img.color());
+        assert_eq!(cropped.dimensions(), (10, 9));
+        assert_eq!(cropped.get_pixel(0, 0), img.get_pixel(3, 2));
+
+        // Padding back what was cropped restores the middle of the image
+        let args = Args::from_positional(pad.params(), "2,1,3,3").unwrap();
+        let padded = pad.apply(&cropped, None, &args).unwrap();
+        assert_eq!(padded.color(), img.color());
+        assert_eq!(padded.dimensions(), (16, 12));
+        assert_eq!(padded.get_pixel(8, 6), img.get_pixel(8, 6));
+
+        let args = Args::from_positional(canvas.params(), "20,10,bottom_right").unwrap();
+        let resized = canvas.apply(img, None, &args).unwrap();
+        assert_eq!(resized.color(), img.color());
+        assert_eq!(resized.dimensions(), (20, 10));
+        assert_eq!(resized.get_pixel(19, 9), img.get_pixel(15, 11));
+        assert_eq!(resized.get_pixel(4, 0), img.get_pixel(0, 2));
+        assert_eq!(resized.get_pixel(3, 0).0[..3], [0, 0, 0]);
+    }
+
+    for bad in ["10,0,7,12", "0,0,16,13"] {
+        let args = Args::from_positional(crop.params(), bad).unwrap();
+        assert!(crop.apply(&img, None, &args).is_err(), "{}", bad);
+    }
+
+    let args = Args::from_positional(pad.params(), "1,1,1,1,constant,255 0 0 0").unwrap();
+    let padded = pad.apply(&img, None, &args).unwrap().to_rgba8();
+    assert_eq!(padded.get_pixel(0, 0).0, [255, 0, 0, 0]);
+    assert_eq!(padded.get_pixel(1, 1).0[3], 255);
+
+    let args = Args::from_positional(canvas.params(), "8,4").unwrap();
+    let centred = canvas.apply(&img, None, &args).unwrap();
+    assert_eq!(centred.get_pixel(0, 0), img.get_pixel(4, 4));
+}
+
+#[test]
+fn stretching_never_leaves_an_empty_image() {
+    let registry = Registry::new();
+    let img = test_image(16, 12);
+    let stretch = registry.get("geometric/stretch").unwrap();
+
+    let args = Args::from_positional(stretch.params(), "0.5,2").unwrap();
+    assert_eq!(
+        stretch.apply(&img, None, &args).unwrap().dimensions(),
+        (8, 24)
+    );
+
+    assert!(Args::from_positional(stretch.params(), "0,1").is_err());
+    let args = Args::from_positional(stretch.params(), "1,0.01").unwrap();
+    assert!(matches!(
+        stretch.apply(&img, None, &args),
+        Err(DipError::InvalidArgument(_))
+    ));
+}
+
+#[test]
+fn mirroring_matches_flips_and_turns() {
+    let registry = Registry::new();
+    let img = test_image(16, 11);
+    let mirror = registry.get("geometric/mirror").unwrap();
+    let expected = [
+        ("x", img.fliph()),
+        ("y", img.flipv()),
+        ("both", img.rotate180()),
+        ("transpose", img.rotate90().fliph()),
+        ("anti_transpose", img.rotate270().fliph()),
+    ];
+    for (axis, expected) in expected {
+        for img in [img.clone(), DynamicImage::ImageRgba16(img.to_rgba16())] {
+            let args = Args::from_positional(mirror.params(), axis).unwrap();
+            let mirrored = mirror.apply(&img, None, &args).unwrap();
+            assert_eq!(mirrored.to_rgb8(), expected.to_rgb8(), "{}", axis);
+            assert_eq!(mirrored.color(), img.color());
+        }
+    }
+
+    assert!(Args::from_positional(mirror.params(), "z").is_err());
+    assert!(dip_core::transform::geometric::mirror(img, "z").is_err());
+}
+
+#[test]
+fn registration_measures_and_undoes_the_shift() {
+    let registry = Registry::new();
+    let img = test_image(64, 48);
+    let translate = registry.get("geometric/translate").unwrap();
+    let args = Args::from_positional(translate.params(), "5,-3,wrap").unwrap();
+    let shifted = translate.apply(&img, None, &args).unwrap();
+
+    let register = registry.get("fft/register").unwrap();
+    let args = Args::from_positional(register.params(), "").unwrap();
+    let output = register
+        .apply_measured(&img, Some(&shifted), &args)
+        .unwrap();
+    assert!((output.measurements["dx"] - 5.0).abs() < 0.5);
+    assert!((output.measurements["dy"] + 3.0).abs() < 0.5);
+    assert_eq!(
+        output.image.to_rgb8().get_pixel(32, 24),
+        img.to_rgb8().get_pixel(32, 24)
+    );
+
+    // Transforms that measure nothing report nothing
+    let output = translate.apply_measured(&img, None, &args).unwrap();
+    assert!(output.measurements.is_empty());
+}
+
+#[test]
+fn convolving_with_a_white_square_is_a_mean_filter() {
+    let registry = Registry::new();
+    let img = test_image(40, 30);
+    let square = DynamicImage::ImageRgb8(RgbImage::from_pixel(5, 5, Rgb([255, 255, 255])));
+
+    let mean = registry.get("filter/mean").unwrap();
+    let args = Args::from_positional(mean.params(), "5").unwrap();
+    let expected = mean.apply(&img, None, &args).unwrap().to_rgb8();
+    let convolve = registry.get("fft/convolve").unwrap();
+    let args = Args::from_positional(convolve.params(), "").unwrap();
+    let result = convolve.apply(&img, Some(&square), &args).unwrap();
+
+    assert_eq!(result.color(), img.color());
+    for (a, b) in result.to_rgb8().pixels().zip(expected.pixels()) {
+        for c in 0..3 {
+            assert!(
+                (a.0[c] as i32 - b.0[c] as i32).abs() <= 1,
+                "{:?} {:?}",
+                a,
+                b
+            );
+        }
+    }
+}
+
+#[test]
+fn manual_notches_need_points() {
+    let registry = Registry::new();
+    let img = test_image(32, 32);
+    let notch = registry.get("fft/notch").unwrap();
+    let apply = |arg| {
+        let args = Args::from_positional(notch.params(), arg).unwrap();
+        notch.apply(&img, None, &args)
+    };
+    assert!(apply("manual").is_err());
+    assert!(apply("manual,4 2 1").is_err());
+    assert!(apply("manual,4 2; -3 5").is_ok());
+    assert!(apply("auto,4 2").is_ok());
+}
+
+#[test]
+fn spectra_flow_through_filters_to_a_true_inverse() {
+    let registry = Registry::new();
+    let img = test_image(24, 18);
+    let step = |state: &Output, name: &str, arg: &str| {
+        let transform = registry.get(name).unwrap();
+        let args = Args::from_positional(transform.params(), arg).unwrap();
+        transform
+            .apply_with_spectrum(&state.image, state.spectrum.as_ref(), None, &args)
+            .unwrap()
+    };
+
+    let spectrum = step(&Output::from(img.clone()), "fft/spectrum", "");
+    assert!(spectrum.spectrum.is_some());
+    assert_eq!(step(&spectrum, "fft/idft", "").image, img);
+
+    // Only the view changes
+    let phase = step(&spectrum, "fft/spectrum", "phase");
+    assert_ne!(phase.image, spectrum.image);
+    assert_eq!(step(&phase, "fft/idft", "").image, img);
+
+    let filtered = step(&spectrum, "fft/filter", "butterworth,high_pass,4");
+    assert!(filtered.spectrum.is_some());
+    let inverse = step(&filtered, "fft/idft", "");
+    let direct = step(
+        &Output::from(img.clone()),
+        "fft/filter",
+        "butterworth,high_pass,4",
+    );
+    assert_eq!(inverse.image, direct.image);
+    assert_ne!(inverse.image, img);
+
+    // Transforms that don't work on spectra see the view
+    let inverted = step(&spectrum, "color/invert", "");
+    assert!(inverted.spectrum.is_none());
+    let direct = step(&Output::from(spectrum.image.clone()), "color/invert", "");
+    assert_eq!(inverted.image, direct.image);
+}
+
+#[test]
+fn padded_spectra_invert_to_the_original_size() {
+    let registry = Registry::new();
+    let img = test_image(24, 18);
+    let run = |name: &str, arg: &str, state: &Output| {
+        let transform = registry.get(name).unwrap();
+        let args = Args::from_positional(transform.params(), arg).unwrap();
+        transform
+            .apply_with_spectrum(&state.image, state.spectrum.as_ref(), None, &args)
+            .unwrap()
+    };
+    let original = Output::from(img.clone());
+
+    let dft = run("fft/dft", "hann,,power_of_two", &original);
+    assert_eq!(dft.image.dimensions(), (32, 32));
+    let dft = run("fft/dft_no_log", ",,size,30,10", &original);
+    assert_eq!(dft.image.dimensions(), (30, 18));
+
+    let spectrum = run("fft/spectrum", ",,,double", &original);
+    assert_eq!(spectrum.image.dimensions(), (48, 36));
+    assert_eq!(run("fft/idft", "", &spectrum).image, img);
+    // A spectrum is padded by transforming its image again
+    let spectrum = run("fft/spectrum", "", &original);
+    let padded = run("fft/spectrum", ",,,double", &spectrum);
+    assert_eq!(padded.image.dimensions(), (48, 36));
+    assert_eq!(run("fft/idft", "", &padded).image, img);
+
+    // Windows are not undone
+    let spectrum = run("fft/spectrum", ",tukey,0.2", &original);
+    let inverse = run("fft/idft", "", &spectrum).image;
+    assert_eq!(inverse.dimensions(), img.dimensions());
+    assert_ne!(inverse, img);
+    assert_eq!(inverse.get_pixel(12, 9), img.get_pixel(12, 9));
+}
+
+#[test]
+fn written_images_read_back_identically() {
+    let img = test_image(20, 10);
+    let path = std::env::temp_dir().join(format!("dip-core-io-{}.png", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    write_image(&img, path).unwrap();
+    let read = read_working_image(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(read.to_rgb8(), img.to_rgb8());
+    assert!(write_image(&img, "out.tga").is_err());
+}
+
+#[test]
+fn deep_images_are_saved_without_losing_precision() {
+    // Values that 8 bits can't hold
+    let img = DynamicImage::ImageRgb16(ImageBuffer::from_fn(20, 10, |x, y| {
+        Rgb([x as u16 * 3001 + 1, y as u16 * 6007 + 3, 1234])
+    }));
+
+    for extension in ["png", "tif", "tiff", "PNG", "TIF"] {
+        let path = std::env::temp_dir().join(format!(
+            "dip-core-io16-{}.{}",
+            std::process::id(),
+            extension
+        ));
+        let path = path.to_str().unwrap();
+
+        write_image(&img, path).unwrap();
+        let read = read_working_image(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(read, img, "{} lost precision", extension);
+
+        // Float images are stored as 16-bit
+        let float = DynamicImage::ImageRgb32F(img.to_rgb32f());
+        write_image(&float, path).unwrap();
+        let read = read_working_image(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(read, img);
+    }
+}
+
+#[test]
+fn contour_errors_instead_of_panicking() {
+    let registry = Registry::new();
+    let transform = registry.get("fourier_desc").unwrap();
+    let args = Args::from_positional(transform.params(), "").unwrap();
+
+    let blank = DynamicImage::new_rgb8(16, 16);
+    assert_eq!(
+        transform.apply(&blank, None, &args).err(),
+        Some(DipError::EmptyContour)
+    );
+
+    // The contour of this square has fewer points than the default term count
+    let small = test_image(8, 8);
+    assert!(transform.apply(&small, None, &args).is_ok());
+
+    // A contour of one point has no size to scale back to
+    let mut dot = RgbImage::new(16, 16);
+    dot.put_pixel(7, 7, Rgb([255, 255, 255]));
+    assert_eq!(
+        transform
+            .apply(&DynamicImage::ImageRgb8(dot), None, &args)
+            .err(),
+        Some(DipError::EmptyContour)
+    );
+}
+
+#[test]
+fn errors_carry_a_stable_code() {
+    let registry = Registry::new();
+    let err = registry.get("nope").err().unwrap();
+    assert_eq!(err.code(), "unknown_transform");
+    assert_eq!(err.to_string(), "Invalid transform \"nope\"");
+}
+
+#[test]
+fn argument_errors_name_the_transform_once() {
+    let registry = Registry::new();
+    let raw = BTreeMap::from([("kernel_size".to_string(), ParamValue::Int(-1))]);
+    let err = registry.resolve_args("filter/mean", &raw).err().unwrap();
+    assert_eq!(err.code(), "invalid_argument");
+    let message = err.to_string();
+    assert!(
+        message.starts_with("Invalid arguments for filter/mean: "),
+        "{}",
+        message
+    );
+    assert_eq!(
+        message.matches("Invalid arguments").count(),
+        1,
+        "{}",
+        message
+    );
+
+    let err = registry.resolve_args("nope", &raw).err().unwrap();
+    assert_eq!(err.code(), "unknown_transform");
+}
@@ -1,3 +1,4 @@
+use dip_core::DipError;
 use serde::Serialize;
 use std::fs;
 use std::path::Path;
@@ -10,39 +11,45 @@ pub struct TreeNode {
 }
 
 #[tauri::command]
-pub fn read_folder(path: String) -> Result<Vec<TreeNode>, String> {
+pub fn read_folder(path: String) -> Result<Vec<TreeNode>, DipError> {
     let root_path = Path::new(&path);
     if root_path.exists() && root_path.is_dir() {
         let mut nodes = Vec::new();
         build_tree(root_path, &mut nodes, root_path.display().to_string());
         Ok(nodes)
     } else {
-        Err("Invalid directory path".to_string())
+        Err(DipError::NotFound(format!(
+            "\"{}\" is not a directory",
+            path
+        )))
     }
 }
 
 fn build_tree(path: &Path, nodes: &mut Vec<TreeNode>, parent_id: String) {
     if let Ok(entries) = fs::read_dir(path) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                let file_name = entry.file_name().into_string().unwrap();
-                let id = format!("{}/{}", parent_id, file_name);
-                if path.is_dir() {
-                    let mut children = Vec::new();
-                    build_tree(&path, &mut children, id.clone());
-                    nodes.push(TreeNode {
-                        id,
-                        name: file_name,
-                        children: Some(children),
-                    });
-                } else {
-                    nodes.push(TreeNode {
-                        id,
-                        name: file_name,
-                        children: None,
-                    });
-                }
+        for entry in entries.flatten() {
+            let path = entry.path();
+            // The id doubles as the path the frontend opens, so a lossy name
+            // would point at the wrong file. Such entries are left out.
+            let file_name = match entry.file_name().into_string() {
+                Ok(file_name) => file_name,
+                Err(_) => continue,
+            };
+            let id = format!("{}/{}", parent_id, file_name);
+            if path.is_dir() {
+                let mut children = Vec::new();
+                build_tree(&path, &mut children, id.clone());
+                nodes.push(TreeNode {
+                    id,
+                    name: file_name,
+                    children: Some(children),
+                });
+            } else {
+                nodes.push(TreeNode {
+                    id,
+                    name: file_name,
+                    children: None,
+                });
             }
         }
     }
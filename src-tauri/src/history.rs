@@ -0,0 +1,497 @@
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+use crate::store::{Entry, ImageId, ImageInfo, ImageStore};
+use dip_core::io::read_working_image;
+use dip_core::transform::registry::{Args, Output, Registry};
+use dip_core::transform::spectrum::Spectrum;
+use dip_core::DipError;
+
+/// Histories are keyed by the id of the image that was opened from disk.
+pub type HistoryId = ImageId;
+
+/// Every this many steps the result is kept in memory, so reconstructing any
+/// step replays at most `KEYFRAME_INTERVAL - 1` operations.
+const KEYFRAME_INTERVAL: usize = 8;
+
+/// One recorded `transform_image` call.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Operation {
+    pub transform: String,
+    pub args: Args,
+    /// How the second image of a binary transform was produced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub img2: Option<OperationLog>,
+}
+
+/// A file and the operations applied to it, enough to reproduce the result.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OperationLog {
+    pub source: String,
+    pub operations: Vec<Operation>,
+}
+
+impl OperationLog {
+    pub fn replay(&self, registry: &Registry) -> Result<DynamicImage, DipError> {
+        let mut state = Output::from(read_working_image(&self.source)?);
+        for op in &self.operations {
+            let img2 = op
+                .img2
+                .as_ref()
+                .map(|log| log.replay(registry))
+                .transpose()?;
+            state = apply_operation(
+                registry,
+                &state.image,
+                state.spectrum.as_ref(),
+                op,
+                img2.as_ref(),
+            )?;
+        }
+        Ok(state.image)
+    }
+}
+
+fn apply_operation(
+    registry: &Registry,
+    img: &DynamicImage,
+    spectrum: Option<&Spectrum>,
+    op: &Operation,
+    img2: Option<&DynamicImage>,
+) -> Result<Output, DipError> {
+    let (transform, args) = registry.resolve_args(&op.transform, op.args.values())?;
+    transform.apply_with_spectrum(img, spectrum, img2, &args)
+}
+
+struct History {
+    log: OperationLog,
+    /// Second images of binary operations, parallel to `log.operations`.
+    operands: Vec<Option<Arc<DynamicImage>>>,
+    /// Images after `step` operations. Step 0 is always present.
+    keyframes: BTreeMap<usize, Entry>,
+    /// Stored images known to show a step, so undo and redo hand them out
+    /// again instead of rebuilding them.
+    images: BTreeMap<usize, StepImage>,
+    cursor: usize,
+}
+
+#[derive(Clone, Copy)]
+struct StepImage {
+    id: ImageId,
+    /// Made by undo or redo rather than by the frontend, so the history frees
+    /// it when the step is discarded.
+    owned: bool,
+}
+
+impl History {
+    fn new(source: String, id: ImageId, img: Entry) -> Self {
+        History {
+            log: OperationLog {
+                source,
+                operations: vec![],
+            },
+            operands: vec![],
+            keyframes: BTreeMap::from([(0, img)]),
+            images: BTreeMap::from([(0, StepImage { id, owned: false })]),
+            cursor: 0,
+        }
+    }
+
+    /// Drops every operation after `step`, as a new edit does to the redo
+    /// stack. Returns the images it owned of the dropped steps, which should
+    /// be removed from the store.
+    fn truncate(&mut self, step: usize) -> Vec<ImageId> {
+        self.log.operations.truncate(step);
+        self.operands.truncate(step);
+        self.keyframes.retain(|&k, _| k <= step);
+        self.cursor = self.cursor.min(step);
+        self.images
+            .split_off(&(step + 1))
+            .into_values()
+            .filter(|image| image.owned)
+            .map(|image| image.id)
+            .collect()
+    }
+
+    /// Appends `op`, whose result is stored as `id` if it is stored at all.
+    fn push(
+        &mut self,
+        op: Operation,
+        operand: Option<Arc<DynamicImage>>,
+        id: Option<ImageId>,
+        result: Entry,
+    ) {
+        self.log.operations.push(op);
+        self.operands.push(operand);
+        self.cursor = self.log.operations.len();
+        if let Some(id) = id {
+            self.images
+                .insert(self.cursor, StepImage { id, owned: false });
+        }
+        if self.cursor % KEYFRAME_INTERVAL == 0 {
+            self.keyframes.insert(self.cursor, result);
+        }
+    }
+
+    fn log_until(&self, step: usize) -> OperationLog {
+        OperationLog {
+            source: self.log.source.clone(),
+            operations: self.log.operations[..step].to_vec(),
+        }
+    }
+
+    /// Everything needed to rebuild the image at `step`, so that the replay
+    /// itself can run without holding the lock.
+    fn replay_plan(&self, step: usize) -> ReplayPlan {
+        let (&start, keyframe) = self
+            .keyframes
+            .range(..=step)
+            .next_back()
+            .expect("keyframe 0 is never removed");
+        ReplayPlan {
+            keyframe: keyframe.clone(),
+            operations: self.log.operations[start..step].to_vec(),
+            operands: self.operands[start..step].to_vec(),
+        }
+    }
+}
+
+struct ReplayPlan {
+    keyframe: Entry,
+    operations: Vec<Operation>,
+    operands: Vec<Option<Arc<DynamicImage>>>,
+}
+
+impl ReplayPlan {
+    fn run(self, registry: &Registry) -> Result<Output, DipError> {
+        let mut state = Output::from((*self.keyframe.image).clone());
+        state.spectrum = self.keyframe.spectrum;
+        for (op, operand) in self.operations.iter().zip(&self.operands) {
+            state = apply_operation(
+                registry,
+                &state.image,
+                state.spectrum.as_ref(),
+                op,
+                operand.as_deref(),
+            )?;
+        }
+        Ok(state)
+    }
+}
+
+/// Edit histories of all opened images, plus which history step each stored
+/// image corresponds to.
+#[derive(Default)]
+pub struct Histories {
+    inner: Mutex<HistoriesInner>,
+}
+
+#[derive(Default)]
+struct HistoriesInner {
+    histories: HashMap<HistoryId, History>,
+    origins: HashMap<ImageId, (HistoryId, usize)>,
+}
+
+impl Histories {
+    /// Starts a history for an image that was just opened from `source`.
+    pub fn open(&self, id: ImageId, source: String, img: Entry) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.histories.insert(id, History::new(source, id, img));
+        inner.origins.insert(id, (id, 0));
+    }
+
+    /// Records a `transform_image` call that turned `img` (and `img2`) into
+    /// `result`. Returns images of discarded steps to remove from the store.
+    #[must_use]
+    pub fn record(
+        &self,
+        img: ImageId,
+        img2: Option<(ImageId, Arc<DynamicImage>)>,
+        transform: &str,
+        args: Args,
+        result: ImageId,
+        result_img: Entry,
+    ) -> Vec<ImageId> {
+        let (operand_log, operand) = match img2 {
+            Some((id, operand)) => (self.log_of(id), Some(operand)),
+            None => (None, None),
+        };
+        let op = Operation {
+            transform: transform.to_string(),
+            args,
+            img2: operand_log,
+        };
+        self.push(img, op, operand, result, result_img)
+    }
+
+    /// Appends `op` after the step of `img`. Any steps that were undone past
+    /// `img` are discarded, and the images the history made for them are
+    /// returned to be removed from the store.
+    fn push(
+        &self,
+        img: ImageId,
+        op: Operation,
+        operand: Option<Arc<DynamicImage>>,
+        result: ImageId,
+        result_img: Entry,
+    ) -> Vec<ImageId> {
+        let mut inner = self.inner.lock().unwrap();
+        let (history_id, step) = match inner.origins.get(&img) {
+            Some(&origin) => origin,
+            None => return vec![],
+        };
+        let history = match inner.histories.get_mut(&history_id) {
+            Some(history) => history,
+            None => return vec![],
+        };
+        let discarded = history.truncate(step);
+        history.push(op, operand, Some(result), result_img);
+        // Images of the discarded steps no longer have a valid log.
+        inner
+            .origins
+            .retain(|_, &mut (h, s)| h != history_id || s <= step);
+        inner.origins.insert(result, (history_id, step + 1));
+        discarded
+    }
+
+    /// Appends `op` to the end of `history`. Only a `result` that was stored
+    /// gets a step to be found under.
+    fn append(
+        &self,
+        history: HistoryId,
+        op: Operation,
+        operand: Option<Arc<DynamicImage>>,
+        result: Option<ImageId>,
+        result_img: Entry,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        let step = match inner.histories.get_mut(&history) {
+            Some(h) => {
+                h.push(op, operand, result, result_img);
+                h.cursor
+            }
+            None => return,
+        };
+        if let Some(result) = result {
+            inner.origins.insert(result, (history, step));
+        }
+    }
+
+    /// Forgets image `id`, once it is gone from the store.
+    pub fn release(&self, id: ImageId) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some((history_id, step)) = inner.origins.remove(&id) {
+            if let Some(history) = inner.histories.get_mut(&history_id) {
+                if history.images.get(&step).map(|image| image.id) == Some(id) {
+                    history.images.remove(&step);
+                }
+            }
+        }
+    }
+
+    /// The log that reproduces image `id`.
+    pub fn log_of(&self, id: ImageId) -> Option<OperationLog> {
+        self.inner.lock().unwrap().log_of(id)
+    }
+
+    /// Moves the cursor of `history` one step and returns the image there,
+    /// rebuilding it only if it is no longer stored.
+    fn step(
+        &self,
+        registry: &Registry,
+        store: &ImageStore,
+        history: HistoryId,
+        forward: bool,
+    ) -> Result<ImageInfo, DipError> {
+        let (step, known, plan) = {
+            let inner = self.inner.lock().unwrap();
+            let h = inner
+                .histories
+                .get(&history)
+                .ok_or_else(|| DipError::NotFound(format!("History {} does not exist", history)))?;
+            let step = if forward {
+                if h.cursor == h.log.operations.len() {
+                    return Err(DipError::NothingToRedo);
+                }
+                h.cursor + 1
+            } else {
+                if h.cursor == 0 {
+                    return Err(DipError::NothingToUndo);
+                }
+                h.cursor - 1
+            };
+            let known = h.images.get(&step).map(|image| image.id);
+            (step, known, h.replay_plan(step))
+        };
+
+        if let Some(info) = known.and_then(|id| store.info(id).ok()) {
+            if let Some(h) = self.inner.lock().unwrap().histories.get_mut(&history) {
+                h.cursor = step;
+            }
+            return Ok(info);
+        }
+        let info = store.insert_output(plan.run(registry)?);
+
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(h) = inner.histories.get_mut(&history) {
+            h.cursor = step;
+            let image = StepImage {
+                id: info.id,
+                owned: true,
+            };
+            if let Some(old) = h.images.insert(step, image) {
+                // Rebuilt by another undo or redo in the meantime
+                if old.owned {
+                    store.remove(old.id).ok();
+                }
+            }
+        }
+        inner.origins.insert(info.id, (history, step));
+        Ok(info)
+    }
+}
+
+impl HistoriesInner {
+    fn log_of(&self, id: ImageId) -> Option<OperationLog> {
+        let (history_id, step) = self.origins.get(&id)?;
+        let history = self.histories.get(history_id)?;
+        (*step <= history.log.operations.len()).then(|| history.log_until(*step))
+    }
+}
+
+#[tauri::command]
+pub fn undo(
+    registry: State<Registry>,
+    store: State<ImageStore>,
+    histories: State<Histories>,
+    history: HistoryId,
+) -> Result<ImageInfo, DipError> {
+    histories.step(&registry, &store, history, false)
+}
+
+#[tauri::command]
+pub fn redo(
+    registry: State<Registry>,
+    store: State<ImageStore>,
+    histories: State<Histories>,
+    history: HistoryId,
+) -> Result<ImageInfo, DipError> {
+    histories.step(&registry, &store, history, true)
+}
+
+/// Returns the JSON log that reproduces image `img` from its source file.
+#[tauri::command]
+pub fn export_history(histories: State<Histories>, img: ImageId) -> Result<String, DipError> {
+    let log = histories
+        .log_of(img)
+        .ok_or_else(|| DipError::NotFound(format!("Image {} has no history", img)))?;
+    serde_json::to_string_pretty(&log)
+        .map_err(|e| DipError::Encode(format!("Failed to serialize history: {}", e)))
+}
+
+/// Replays an exported log and opens the result with a fresh history.
+#[tauri::command]
+pub fn import_history(
+    registry: State<Registry>,
+    store: State<ImageStore>,
+    histories: State<Histories>,
+    log: String,
+) -> Result<ImageInfo, DipError> {
+    import(&registry, &store, &histories, &log)
+}
+
+/// Only the source and the final result are stored. The steps between them
+/// are rebuilt from the keyframes when undo or redo reaches them.
+fn import(
+    registry: &Registry,
+    store: &ImageStore,
+    histories: &Histories,
+    log: &str,
+) -> Result<ImageInfo, DipError> {
+    let log: OperationLog = serde_json::from_str(log)
+        .map_err(|e| DipError::InvalidArgument(format!("Failed to parse history: {}", e)))?;
+
+    let img = read_working_image(&log.source)?;
+    let source = store.insert(img);
+    let mut state = store.entry(source.id)?;
+    histories.open(source.id, log.source.clone(), state.clone());
+
+    let mut info = source;
+    let last = log.operations.len();
+    for (step, op) in (1..).zip(log.operations) {
+        let operand = op
+            .img2
+            .as_ref()
+            .map(|log| log.replay(registry).map(Arc::new))
+            .transpose()?;
+        let output = apply_operation(
+            registry,
+            &state.image,
+            state.spectrum.as_ref(),
+            &op,
+            operand.as_deref(),
+        )?;
+        state = output.into();
+        let result = (step == last).then(|| store.insert_entry(state.clone()));
+        if let Some(result) = result {
+            info = result;
+        }
+        histories.append(
+            source.id,
+            op,
+            operand,
+            result.map(|result| result.id),
+            state.clone(),
+        );
+    }
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn importing_stores_only_the_source_and_the_result() {
+        let path = std::env::temp_dir().join(format!("dip-import-{}.png", std::process::id()));
+        let source = DynamicImage::ImageRgb8(RgbImage::from_fn(12, 8, |x, y| {
+            Rgb([(x * 20) as u8, (y * 30) as u8, 90])
+        }));
+        source.save(&path).unwrap();
+
+        let registry = Registry::new();
+        let invert = registry.get("color/invert").unwrap();
+        let op = Operation {
+            transform: "color/invert".to_string(),
+            args: Args::from_positional(invert.params(), "").unwrap(),
+            img2: None,
+        };
+        let log = OperationLog {
+            source: path.to_str().unwrap().to_string(),
+            operations: vec![op; KEYFRAME_INTERVAL + 2],
+        };
+        let (store, histories) = (ImageStore::default(), Histories::default());
+        let info = import(
+            &registry,
+            &store,
+            &histories,
+            &serde_json::to_string(&log).unwrap(),
+        )
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(*store.get(info.id).unwrap(), source);
+        // The steps in between are rebuilt on demand
+        let history = histories.inner.lock().unwrap().origins[&info.id].0;
+        let undone = histories.step(&registry, &store, history, false).unwrap();
+        let mut inverted = source;
+        inverted.invert();
+        assert_eq!(*store.get(undone.id).unwrap(), inverted);
+        assert_eq!(store.len(), 3);
+    }
+}
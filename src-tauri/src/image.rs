@@ -1,173 +1,136 @@
-use crate::transform::{binary_op, color, fft, filter, fourier_desc, geometric};
+use crate::history::Histories;
+use crate::job::{JobId, Jobs};
+use crate::store::{ImageId, ImageInfo, ImageStore};
+use dip_core::io::{encode_image_to_bmp, read_working_image, write_image};
+use dip_core::transform::registry::{Measurements, ParamValue, Registry, TransformInfo};
+use dip_core::DipError;
 
-use base64::engine::general_purpose::STANDARD;
-use base64::Engine;
-use image::io::Reader as ImageReader;
-use image::{DynamicImage, ImageFormat};
-use std::io::{BufWriter, Cursor};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tauri::http::{Request, Response, ResponseBuilder};
+use tauri::{AppHandle, Manager, State, Window};
 
-pub fn read_image(path: String) -> Result<DynamicImage, String> {
-    let img = ImageReader::open(&path)
-        .map_err(|e| format!("Failed to open image: {}", e))?
-        .with_guessed_format()
-        .map_err(|e| format!("Failed to guess image format: {}", e))?
-        .decode()
-        .map_err(|e| format!("Failed to decode image: {}", e))?;
-    Ok(img)
-}
+/// Serves `dip://localhost/<id>` as BMP. With `?size=<n>` the image is
+/// downscaled to fit in `n`×`n`, which is what thumbnails should ask for.
+pub fn image_protocol(
+    app: &AppHandle,
+    request: &Request,
+) -> Result<Response, Box<dyn std::error::Error>> {
+    let path = request.uri().rsplit('/').next().unwrap_or_default();
+    let (id, query) = path.split_once('?').unwrap_or((path, ""));
+    let size = query
+        .split('&')
+        .find_map(|p| p.strip_prefix("size="))
+        .and_then(|s| s.parse::<u32>().ok());
 
-pub fn encode_image_to_base64(img: DynamicImage) -> Result<String, String> {
-    let mut buf = Vec::new();
-    {
-        let mut writer = BufWriter::new(Cursor::new(&mut buf));
-        img.write_to(&mut writer, ImageFormat::Bmp)
-            .map_err(|e| format!("Failed to write image to buffer: {}", e))?;
-    }
-    let base64_img = STANDARD.encode(&buf);
-    Ok(base64_img)
+    let store = app.state::<ImageStore>();
+    let img = match id.parse::<ImageId>() {
+        Ok(id) => store.get(id),
+        Err(_) => Err(DipError::NotFound(format!("Invalid image id \"{}\"", id))),
+    };
+    let img = match img {
+        Ok(img) => img,
+        Err(e) => {
+            return ResponseBuilder::new()
+                .status(404)
+                .body(e.to_string().into_bytes())
+        }
+    };
+    let buf = match size {
+        Some(size) if size < img.width().max(img.height()) => {
+            encode_image_to_bmp(&img.thumbnail(size, size))?
+        }
+        _ => encode_image_to_bmp(&img)?,
+    };
+    ResponseBuilder::new().mimetype("image/bmp").body(buf)
 }
 
 #[tauri::command]
-pub fn open_image(path: String) -> Result<String, String> {
-    // Always open in RGB8 format
-    let img = DynamicImage::ImageRgb8(read_image(path)?.to_rgb8());
-    let base64_img = encode_image_to_base64(img)?;
-    Ok(base64_img)
+pub fn open_image(
+    store: State<ImageStore>,
+    histories: State<Histories>,
+    path: String,
+) -> Result<ImageInfo, DipError> {
+    let info = store.insert(read_working_image(&path)?);
+    histories.open(info.id, path, store.entry(info.id)?);
+    Ok(info)
 }
 
+/// Frees image `img`, such as a preview that was not kept. The steps of its
+/// history stay, so undo and redo can still rebuild it.
 #[tauri::command]
-pub fn save_image(path: String, img: String) -> Result<(), String> {
-    let img = image::load_from_memory(&STANDARD.decode(img).unwrap())
-        .map_err(|e| format!("Failed to load image from base64: {}", e))?;
-    if path.ends_with(".jpg") || path.ends_with(".jpeg") {
-        img.save_with_format(&path, ImageFormat::Jpeg)
-            .map_err(|e| format!("Failed to save image: {}", e))?;
-    } else if path.ends_with(".png") {
-        img.save_with_format(&path, ImageFormat::Png)
-            .map_err(|e| format!("Failed to save image: {}", e))?;
-    } else if path.ends_with(".bmp") {
-        img.save_with_format(&path, ImageFormat::Bmp)
-            .map_err(|e| format!("Failed to save image: {}", e))?;
-    } else {
-        return Err("Unsupported image format".to_string());
-    }
+pub fn release_image(
+    store: State<ImageStore>,
+    histories: State<Histories>,
+    img: ImageId,
+) -> Result<(), DipError> {
+    store.remove(img)?;
+    histories.release(img);
     Ok(())
 }
 
 #[tauri::command]
+pub fn save_image(store: State<ImageStore>, path: String, img: ImageId) -> Result<(), DipError> {
+    let img = store.get(img)?;
+    write_image(&img, &path)
+}
+
+#[tauri::command]
+pub fn list_transforms(registry: State<Registry>) -> Vec<TransformInfo> {
+    registry.list()
+}
+
+/// The transformed image and whatever the transform measured, e.g. `dx` and
+/// `dy` for `fft/register`.
+#[derive(Serialize)]
+pub struct TransformOutput {
+    #[serde(flatten)]
+    info: ImageInfo,
+    measurements: Measurements,
+}
+
+/// Runs off the main thread as job `job_id`, which reports its progress as
+/// `transform_progress` events and can be stopped with `cancel_transform`.
+#[tauri::command(async)]
+#[allow(clippy::too_many_arguments)]
 pub fn transform_image(
-    img: String,  // base64
-    img2: String, // base64
+    window: Window,
+    registry: State<Registry>,
+    store: State<ImageStore>,
+    histories: State<Histories>,
+    jobs: State<Jobs>,
+    job_id: JobId,
+    img: ImageId,
+    img2: Option<ImageId>,
     transform: String,
-    transform_arg: String,
-) -> Result<String, String> {
-    let img = image::load_from_memory(&STANDARD.decode(img).unwrap())
-        .map_err(|e| format!("Failed to load image from base64: {}", e))?;
-    // load to img2 but use Option to handle the case where img2 is empty
-    let img2 = if img2.is_empty() {
-        None
-    } else {
-        Some(
-            image::load_from_memory(&STANDARD.decode(img2).unwrap())
-                .map_err(|e| format!("Failed to load image from base64: {}", e))?,
-        )
-    };
-    let transformed_img = match transform.as_str() {
-        "color/invert" => color::invert(img),
-        "color/exponential" => color::exponential(img, transform_arg.parse::<f32>().ok()),
-        "color/hist_equalize" => color::hist_equalize(img),
-        "color/to_gray" => color::to_gray(img),
-        "color/to_binary" => color::to_binary(img, transform_arg.parse::<f32>().ok()),
-        "geometric/translate" => {
-            let args: Vec<&str> = transform_arg.split(',').map(|s| s.trim()).collect();
-            if args.len() != 2 {
-                return Err("Invalid arguments for translate".to_string());
-            }
-            geometric::translate(
-                img,
-                args[0].parse::<i32>().ok(),
-                args[1].parse::<i32>().ok(),
-            )
-        }
-        "geometric/rotate" => geometric::rotate(img, transform_arg.parse::<f32>().ok()),
-        "geometric/resize" => {
-            let args: Vec<&str> = transform_arg.split(',').map(|s| s.trim()).collect();
-            if args.len() != 2 {
-                return Err("Invalid arguments for resize".to_string());
-            }
-            geometric::resize(
-                img,
-                args[0].parse::<u32>().ok(),
-                args[1].parse::<u32>().ok(),
-            )
-        }
-        "geometric/mirror" => geometric::mirror(img, transform_arg.as_str()),
-        "geometric/stretch" => {
-            let args: Vec<&str> = transform_arg.split(',').map(|s| s.trim()).collect();
-            if args.len() != 2 {
-                return Err("Invalid arguments for stretch".to_string());
-            }
-            geometric::stretch(
-                img,
-                args[0].parse::<f32>().ok(),
-                args[1].parse::<f32>().ok(),
-            )
-        }
-        "binary_op/add" => binary_op::binary_op(&img, &img2.unwrap(), "add"),
-        "binary_op/sub" => binary_op::binary_op(&img, &img2.unwrap(), "sub"),
-        "binary_op/mul" => binary_op::binary_op(&img, &img2.unwrap(), "mul"),
-        "binary_op/div" => binary_op::binary_op(&img, &img2.unwrap(), "div"),
-        "filter/mean" => filter::mean(&img, transform_arg.parse::<u32>().unwrap_or(3)),
-        "filter/median" => filter::median(&img, transform_arg.parse::<u32>().unwrap_or(3)),
-        "filter/gaussian" => {
-            let args: Vec<&str> = transform_arg.split(',').map(|s| s.trim()).collect();
-            if args.len() != 2 {
-                return Err("Invalid arguments for gaussian".to_string());
-            }
-            filter::gaussian(
-                &img,
-                args[0].parse::<usize>().unwrap_or(3),
-                args[1].parse::<f32>().unwrap_or(1.),
-            )
-        }
-        "filter/sobel" => filter::sobel(&img, transform_arg.as_str()),
-        "filter/laplacian" => filter::laplacian(&img, transform_arg.parse::<u8>().unwrap_or(8)),
-        "filter/prewitt" => filter::prewitt(&img, transform_arg.as_str()),
-        "filter/roberts" => filter::roberts(&img, transform_arg.as_str()),
-        "filter/sobel_sharpen" => filter::sobel_sharpen(&img, transform_arg.as_str()),
-        "filter/laplacian_sharpen" => {
-            filter::laplacian_sharpen(&img, transform_arg.parse::<u8>().unwrap_or(8))
-        }
-        "filter/prewitt_sharpen" => filter::prewitt_sharpen(&img, transform_arg.as_str()),
-        "filter/roberts_sharpen" => filter::roberts_sharpen(&img, transform_arg.as_str()),
-        "fft/dft" => fft::dft(&img),
-        "fft/dft_non_shifted_no_log" => fft::dft_non_shifted_no_log(&img),
-        "fft/dft_non_shifted" => fft::dft_non_shifted(&img),
-        "fft/dft_no_log" => fft::dft_no_log(&img),
-        "fft/idft" => fft::idft(&img),
-        "fft/idft_non_shifted" => fft::idft_non_shifted(&img),
-        "fft/shift_to_center" => fft::shift_to_center(&img),
-        "fft/homomorphic" => {
-            let args: Vec<&str> = transform_arg.split(',').map(|s| s.trim()).collect();
-            if args.len() != 4 {
-                return Err("Invalid arguments for homomorphic filtering".to_string());
-            }
-            fft::homomorphic(
-                &img,
-                args[0].parse::<f32>().ok(),
-                args[1].parse::<f32>().ok(),
-                args[2].parse::<f32>().ok(),
-                args[3].parse::<f32>().ok(),
-            )
-        }
-        "fft/dft_idft" => fft::dft_idft(&img),
-        "fourier_desc" => {
-            fourier_desc::reconstruct(&img, transform_arg.parse::<usize>().unwrap_or(64))
-        }
+    args: BTreeMap<String, ParamValue>,
+) -> Result<TransformOutput, DipError> {
+    let (transform, args) = registry.resolve_args(&transform, &args)?;
 
-        _ => return Err("Invalid transform".to_string()),
-    };
+    let entry = store.entry(img)?;
+    let image2 = img2.map(|id| store.get(id)).transpose()?;
+    let job = jobs.start(job_id.clone(), window)?;
+    let output = job.run(|| {
+        transform.apply_with_spectrum(
+            &entry.image,
+            entry.spectrum.as_ref(),
+            image2.as_deref(),
+            &args,
+        )
+    });
+    jobs.finish(&job_id);
+    let mut output = output?;
+    let measurements = std::mem::take(&mut output.measurements);
 
-    let base64_img = encode_image_to_base64(transformed_img)?;
-    Ok(base64_img)
+    let info = store.insert_output(output);
+    let discarded = histories.record(
+        img,
+        img2.zip(image2),
+        transform.name(),
+        args,
+        info.id,
+        store.entry(info.id)?,
+    );
+    store.remove_all(&discarded);
+    Ok(TransformOutput { info, measurements })
 }
@@ -0,0 +1,73 @@
+use dip_core::transform::job::{Job, Progress};
+use dip_core::DipError;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tauri::{State, Window};
+
+/// Chosen by the frontend, so it knows the id before the transform returns.
+pub type JobId = String;
+
+#[derive(Clone, Serialize)]
+struct TransformProgressPayload {
+    job_id: JobId,
+    #[serde(flatten)]
+    progress: Progress,
+}
+
+/// Transforms that are running, so that they can be cancelled.
+#[derive(Default)]
+pub struct Jobs {
+    inner: Mutex<HashMap<JobId, Arc<Job>>>,
+}
+
+impl Jobs {
+    /// Starts a job that emits `transform_progress` to `window` whenever the
+    /// current pass gets at least another percent further.
+    pub fn start(&self, job_id: JobId, window: Window) -> Result<Arc<Job>, DipError> {
+        let mut jobs = self.inner.lock().unwrap();
+        if jobs.contains_key(&job_id) {
+            return Err(DipError::InvalidArgument(format!(
+                "Job \"{}\" is already running",
+                job_id
+            )));
+        }
+        let payload_id = job_id.clone();
+        let job = Job::new(move |progress| {
+            let Progress { done, total, .. } = progress;
+            if done == total || done * 100 / total != (done - 1) * 100 / total {
+                let payload = TransformProgressPayload {
+                    job_id: payload_id.clone(),
+                    progress,
+                };
+                let _ = window.emit("transform_progress", payload);
+            }
+        });
+        jobs.insert(job_id, job.clone());
+        Ok(job)
+    }
+
+    pub fn finish(&self, job_id: &str) {
+        self.inner.lock().unwrap().remove(job_id);
+    }
+
+    pub fn cancel(&self, job_id: &str) -> Result<(), DipError> {
+        match self.inner.lock().unwrap().get(job_id) {
+            Some(job) => {
+                job.cancel();
+                Ok(())
+            }
+            None => Err(DipError::NotFound(format!(
+                "Job \"{}\" is not running",
+                job_id
+            ))),
+        }
+    }
+}
+
+/// Asks a running `transform_image` to stop; it then fails with the
+/// `cancelled` error code.
+#[tauri::command]
+pub fn cancel_transform(jobs: State<Jobs>, job_id: JobId) -> Result<(), DipError> {
+    jobs.cancel(&job_id)
+}
@@ -2,9 +2,16 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod folder;
+mod history;
 mod image;
+mod job;
 mod menu;
-mod transform;
+mod store;
+
+use dip_core::transform::registry::Registry;
+use history::Histories;
+use job::Jobs;
+use store::ImageStore;
 
 #[derive(Clone, serde::Serialize)]
 struct MenuEventPayload {
@@ -26,11 +33,23 @@ fn main() {
                 .unwrap();
             println!("menu event: {:?}", event.menu_item_id())
         })
+        .manage(Registry::new())
+        .manage(ImageStore::default())
+        .manage(Histories::default())
+        .manage(Jobs::default())
+        .register_uri_scheme_protocol("dip", image::image_protocol)
         .invoke_handler(tauri::generate_handler![
             folder::read_folder,
             image::open_image,
+            image::list_transforms,
             image::transform_image,
+            job::cancel_transform,
             image::save_image,
+            image::release_image,
+            history::undo,
+            history::redo,
+            history::export_history,
+            history::import_history,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
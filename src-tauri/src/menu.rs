@@ -11,7 +11,21 @@ pub fn create_menu() -> Result<Menu, String> {
             .add_item(CustomMenuItem::new("geometric/rotate".to_string(), "旋转"))
             .add_item(CustomMenuItem::new("geometric/resize".to_string(), "缩放"))
             .add_item(CustomMenuItem::new("geometric/mirror".to_string(), "镜像"))
-            .add_item(CustomMenuItem::new("geometric/stretch".to_string(), "拉伸")),
+            .add_item(CustomMenuItem::new("geometric/stretch".to_string(), "拉伸"))
+            .add_item(CustomMenuItem::new(
+                "geometric/affine".to_string(),
+                "仿射变换",
+            ))
+            .add_item(CustomMenuItem::new(
+                "geometric/perspective".to_string(),
+                "透视变换",
+            ))
+            .add_item(CustomMenuItem::new("geometric/crop".to_string(), "裁剪"))
+            .add_item(CustomMenuItem::new("geometric/pad".to_string(), "边缘填充"))
+            .add_item(CustomMenuItem::new(
+                "geometric/canvas".to_string(),
+                "画布大小",
+            )),
     );
 
     let color_menu = Submenu::new(
@@ -47,6 +61,10 @@ pub fn create_menu() -> Result<Menu, String> {
         Menu::new()
             .add_item(CustomMenuItem::new("filter/mean".to_string(), "均值滤波"))
             .add_item(CustomMenuItem::new("filter/median".to_string(), "中值滤波"))
+            .add_item(CustomMenuItem::new(
+                "filter/rank".to_string(),
+                "排序统计滤波",
+            ))
             .add_item(CustomMenuItem::new(
                 "filter/gaussian".to_string(),
                 "高斯滤波",
@@ -82,6 +100,10 @@ pub fn create_menu() -> Result<Menu, String> {
             .add_item(CustomMenuItem::new(
                 "filter/roberts".to_string(),
                 "Roberts边缘检测",
+            ))
+            .add_item(CustomMenuItem::new(
+                "filter/custom".to_string(),
+                "自定义卷积核",
             )),
     );
 
@@ -104,6 +126,7 @@ pub fn create_menu() -> Result<Menu, String> {
                 "fft/dft_no_log".to_string(),
                 "无值域压缩DFT",
             ))
+            .add_item(CustomMenuItem::new("fft/spectrum".to_string(), "复数频谱"))
             .add_item(CustomMenuItem::new(
                 "fft/idft".to_string(),
                 "离散傅里叶逆变换 (iDFT)",
@@ -120,7 +143,12 @@ pub fn create_menu() -> Result<Menu, String> {
             .add_item(CustomMenuItem::new(
                 "fft/homomorphic".to_string(),
                 "同态滤波",
-            )),
+            ))
+            .add_item(CustomMenuItem::new("fft/filter".to_string(), "频域滤波"))
+            .add_item(CustomMenuItem::new("fft/notch".to_string(), "陷波滤波"))
+            .add_item(CustomMenuItem::new("fft/convolve".to_string(), "频域卷积"))
+            .add_item(CustomMenuItem::new("fft/correlate".to_string(), "模板匹配"))
+            .add_item(CustomMenuItem::new("fft/register".to_string(), "图像配准")),
     );
 
     let fourier_desc_menu = Submenu::new(
@@ -0,0 +1,118 @@
+use dip_core::transform::registry::Output;
+use dip_core::transform::spectrum::Spectrum;
+use dip_core::DipError;
+use image::DynamicImage;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+pub type ImageId = u64;
+
+/// What the frontend gets instead of pixels: a handle and the image size.
+#[derive(Clone, Copy, Serialize)]
+pub struct ImageInfo {
+    pub id: ImageId,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A stored image and, if it is a view of one, the spectrum behind it.
+#[derive(Clone)]
+pub struct Entry {
+    pub image: Arc<DynamicImage>,
+    pub spectrum: Option<Spectrum>,
+}
+
+impl From<Output> for Entry {
+    fn from(output: Output) -> Self {
+        Entry {
+            image: Arc::new(output.image),
+            spectrum: output.spectrum,
+        }
+    }
+}
+
+/// Decoded images kept in the backend and referred to by handle.
+///
+/// Images are shared as `Arc`s so transforms never run while the lock is held.
+/// The handle of a spectrum view also stands for the complex spectrum, so
+/// transforms that work on spectra can pick it up again.
+#[derive(Default)]
+pub struct ImageStore {
+    inner: Mutex<StoreInner>,
+}
+
+#[derive(Default)]
+struct StoreInner {
+    next_id: ImageId,
+    images: HashMap<ImageId, Entry>,
+}
+
+impl ImageStore {
+    pub fn insert(&self, img: DynamicImage) -> ImageInfo {
+        self.insert_output(img.into())
+    }
+
+    /// Stores the image of a transform result along with its spectrum.
+    pub fn insert_output(&self, output: Output) -> ImageInfo {
+        self.insert_entry(output.into())
+    }
+
+    /// Stores an image that may also be held elsewhere, such as a keyframe.
+    pub fn insert_entry(&self, entry: Entry) -> ImageInfo {
+        let (width, height) = (entry.image.width(), entry.image.height());
+        let mut inner = self.inner.lock().unwrap();
+        inner.next_id += 1;
+        let id = inner.next_id;
+        inner.images.insert(id, entry);
+        ImageInfo { id, width, height }
+    }
+
+    /// Drops image `id` and its spectrum. Readers that already hold them keep
+    /// their `Arc`s.
+    pub fn remove(&self, id: ImageId) -> Result<(), DipError> {
+        self.inner
+            .lock()
+            .unwrap()
+            .images
+            .remove(&id)
+            .map(|_| ())
+            .ok_or_else(|| DipError::NotFound(format!("Image {} does not exist", id)))
+    }
+
+    /// Drops every image of `ids` that is still stored.
+    pub fn remove_all(&self, ids: &[ImageId]) {
+        let mut inner = self.inner.lock().unwrap();
+        for id in ids {
+            inner.images.remove(id);
+        }
+    }
+
+    pub fn info(&self, id: ImageId) -> Result<ImageInfo, DipError> {
+        let image = self.get(id)?;
+        Ok(ImageInfo {
+            id,
+            width: image.width(),
+            height: image.height(),
+        })
+    }
+
+    pub fn get(&self, id: ImageId) -> Result<Arc<DynamicImage>, DipError> {
+        self.entry(id).map(|entry| entry.image)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.inner.lock().unwrap().images.len()
+    }
+
+    pub fn entry(&self, id: ImageId) -> Result<Entry, DipError> {
+        self.inner
+            .lock()
+            .unwrap()
+            .images
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| DipError::NotFound(format!("Image {} does not exist", id)))
+    }
+}
@@ -1,8 +0,0 @@
-pub mod binary_op;
-pub mod color;
-pub mod fft;
-pub mod filter;
-pub mod geometric;
-pub mod fourier_desc;
-
-mod utils;
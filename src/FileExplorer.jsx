@@ -167,7 +167,9 @@ function FileExplorer() {
                 extension === "bmp" ||
                 extension === "jpg" ||
                 extension === "jpeg" ||
-                extension === "png"
+                extension === "png" ||
+                extension === "tif" ||
+                extension === "tiff"
               ) {
                 if (path in historyTrees) {
                   let treeNode = historyTrees[path].children[0];
@@ -200,7 +202,7 @@ function FileExplorer() {
                   }
                 } else {
                   invoke("open_image", { path })
-                    .then((imgBase64) => {
+                    .then((image) => {
                       let targetTabset =
                         tabsModel.getActiveTabset() ||
                         tabsModel.getFirstTabSet();
@@ -210,7 +212,7 @@ function FileExplorer() {
                           component: "img",
                           name: filename,
                           config: {
-                            data: imgBase64,
+                            data: image,
                             filename,
                             path,
                           },
@@ -226,13 +228,13 @@ function FileExplorer() {
                             name: `${filename}`,
                             id: id,
                             children: [],
-                            metadata: { img: imgBase64, path },
+                            metadata: { img: image, path },
                           },
                         ],
                       };
                     })
                     .catch((error) => {
-                      console.log(`Error loading image "${path}", ${error}`);
+                      console.log(`Error loading image "${path}", ${error.message}`);
                     });
                 }
               }
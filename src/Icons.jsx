@@ -23,6 +23,8 @@ const FileIcon = ({ filename }) => {
     case "jpg":
     case "jpeg":
     case "png":
+    case "tif":
+    case "tiff":
       return <FaFileImage color={imageFileColor} className="icon" />;
     default:
       return <FaFile color={otherFileColor} className="icon" />;
@@ -14,6 +14,7 @@ import { TabsLayoutContext } from "./contexts/TabsLayoutContext";
 import TransformDialog from "./TransformDialog";
 
 import { showPopup } from "./PopupMenu";
+import { imageSrc } from "./utils";
 
 import "./style/Previewer.css";
 import { writeBinaryFile } from "@tauri-apps/api/fs";
@@ -47,27 +48,9 @@ function Previewer() {
     closeTransformDialog();
   };
 
-  const getImageDimensions = (base64) => {
-    return new Promise((resolve) => {
-      const img = new Image();
-      img.onload = () => {
-        resolve({ width: img.width, height: img.height });
-      };
-      img.src = `data:image/bmp;base64,${base64}`;
-    });
-  };
-
   const ImageWithSize = ({ node }) => {
-    const [dimensions, setDimensions] = useState({ width: 0, height: 0 });
     const config = node.getConfig();
 
-    useEffect(() => {
-      (async () => {
-        const dims = await getImageDimensions(config.data);
-        setDimensions(dims);
-      })();
-    }, []);
-
     return (
       <div
         className="image-container"
@@ -92,7 +75,7 @@ function Previewer() {
                     filters: [
                       {
                         name: "Image",
-                        extensions: ["bmp", "jpg", "jpeg", "png"],
+                        extensions: ["bmp", "jpg", "jpeg", "png", "tif", "tiff"],
                       },
                     ],
                   }).catch((error) => {
@@ -100,7 +83,7 @@ function Previewer() {
                   });
                   await invoke("save_image", {
                     path,
-                    img: config.data,
+                    img: config.data.id,
                   });
                   await message(
                     `图片"${node.getName()}"已成功保存至"${path}"`,
@@ -116,9 +99,9 @@ function Previewer() {
           }
         }}
       >
-        <img src={`data:image/bmp;base64,${config.data}`} alt={config.name} />
+        <img src={imageSrc(config.data)} alt={config.name} />
         <p className="image-size">
-          {dimensions.width} x {dimensions.height}
+          {config.data.width} x {config.data.height}
         </p>
       </div>
     );
@@ -195,7 +178,7 @@ function Previewer() {
                       filters: [
                         {
                           name: "Image",
-                          extensions: ["bmp", "jpg", "jpeg", "png"],
+                          extensions: ["bmp", "jpg", "jpeg", "png", "tif", "tiff"],
                         },
                       ],
                     }).catch((error) => {
@@ -203,7 +186,7 @@ function Previewer() {
                     });
                     await invoke("save_image", {
                       path,
-                      img: node.getConfig().data,
+                      img: node.getConfig().data.id,
                     });
                     await message(
                       `图片"${node.getName()}"已成功保存至"${path}"`,
@@ -1,5 +1,6 @@
-import { useState, useContext, useEffect } from "react";
+import { useState, useContext, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/tauri";
+import { listen } from "@tauri-apps/api/event";
 
 import {
   TextInput,
@@ -13,6 +14,7 @@ import {
   Grid,
   Modal,
   Center,
+  Text,
 } from "@mantine/core";
 import { Actions } from "flexlayout-react";
 
@@ -25,6 +27,7 @@ import {
 import { FileInfoContext } from "./contexts/FileInfoContext";
 import { TabsLayoutContext } from "./contexts/TabsLayoutContext";
 import {
+  imageSrc,
   searchTreeNodeByIdAsync,
   searchTreeNodeInTreesById,
   extractHistories,
@@ -41,6 +44,11 @@ const transformNameMap = {
   "geometric/translate": "平移",
   "geometric/mirror": "镜像",
   "geometric/stretch": "拉伸",
+  "geometric/affine": "仿射变换",
+  "geometric/perspective": "透视变换",
+  "geometric/crop": "裁剪",
+  "geometric/pad": "边缘填充",
+  "geometric/canvas": "画布大小",
   "binary_op/add": "加法",
   "binary_op/sub": "减法",
   "binary_op/mul": "乘法",
@@ -48,6 +56,7 @@ const transformNameMap = {
   "filter/mean": "均值滤波",
   "filter/gaussian": "高斯滤波",
   "filter/median": "中值滤波",
+  "filter/rank": "排序统计滤波",
   "filter/sobel": "Sobel算子",
   "filter/roberts": "Roberts算子",
   "filter/prewitt": "Prewitt算子",
@@ -56,54 +65,38 @@ const transformNameMap = {
   "filter/prewitt_sharpen": "Prewitt锐化",
   "filter/laplacian_sharpen": "Laplacian锐化",
   "filter/roberts_sharpen": "Roberts锐化",
+  "filter/custom": "自定义卷积",
   "fft/dft": "DFT",
   "fft/dft_non_shifted": "非平移DFT",
   "fft/dft_non_shifted_no_log": "非平移无值域压缩DFT",
   "fft/dft_no_log": "无值域压缩DFT",
+  "fft/spectrum": "复数频谱",
   "fft/idft": "iDFT",
   "fft/idft_non_shifted": "非平移iDFT",
   "fft/shift_to_center": "平移至中心",
   "fft/homomorphic": "同态滤波",
+  "fft/filter": "频域滤波",
+  "fft/notch": "陷波滤波",
+  "fft/convolve": "频域卷积",
+  "fft/correlate": "模板匹配",
   "fft/dft_idft": "DFT+iDFT",
+  "fft/register": "图像配准",
   "fourier_desc": "傅里叶描述子",
 };
 
-const argHintMap = {
-  "color/to_gray": "无需参数",
-  "color/invert": "无需参数",
-  "color/to_binary": "输入阈值 (格式: x) (x取值范围为归一化后的0-1)",
-  "color/exponential": "输入指数 (格式: x) (x为浮点数)",
-  "color/hist_equalize": "无需参数",
-  "geometric/rotate": "输入角度 (格式: x) (单位：角度, x=360N时为原图)",
-  "geometric/resize": "输入缩放后尺寸 (格式: x,y) (单位：像素)",
-  "geometric/translate": "输入平移距离 (格式: x,y) (单位：像素, x=y=0时为原图)",
-  "geometric/mirror": "输入镜像轴 (格式: a) (其中a可选值为x或y)",
-  "geometric/stretch": "输入拉伸比例 (格式: x,y) (比例, x=y=1时为原图)",
-  "binary_op/add": "无需参数",
-  "binary_op/sub": "无需参数",
-  "binary_op/mul": "无需参数",
-  "binary_op/div": "无需参数",
-  "filter/mean": "输入滤波器尺寸 (格式: k) (k为奇数, 单位：像素)",
-  "filter/gaussian": "输入滤波器尺寸和标准差 (格式: k,s) (k为奇数, s为浮点数)",
-  "filter/median": "输入滤波器尺寸 (格式: k) (k为奇数, 单位：像素)",
-  "filter/sobel": "输入方向 (格式: d) (d可选值为v或h)",
-  "filter/roberts": "输入方向 (格式: d) (d可选值为/或\\)",
-  "filter/prewitt": "输入方向 (格式: d) (d可选值为v或h)",
-  "filter/laplacian": "输入邻域元素个数 (格式: n) (n=4为四邻域, n=8为八邻域)",
-  "filter/sobel_sharpen": "输入方向 (格式: d) (d可选值为v或h)",
-  "filter/roberts_sharpen": "输入方向 (格式: d) (d可选值为/或\\)",
-  "filter/prewitt_sharpen": "输入方向 (格式: d) (d可选值为v或h)",
-  "filter/laplacian_sharpen": "输入邻域元素个数 (格式: n) (n=4为四邻域, n=8为八邻域)",
-  "fft/dft": "无需参数",
-  "fft/dft_non_shifted": "无需参数",
-  "fft/dft_non_shifted_no_log": "无需参数",
-  "fft/dft_no_log": "无值域压缩DFT",
-  "fft/idft": "无需参数",
-  "fft/idft_non_shifted": "无需参数",
-  "fft/shift_to_center": "无需参数",
-  "fft/homomorphic": "输入高斯同态滤波参数 (格式: r_l,r_h,c,d0) (r_l,r_h,c,d0为浮点数, 例: 0.3,2,2,10)",
-  "fft/dft_idft": "无需参数",
-  "fourier_desc": "输入截断项数 (格式: n) (n为整数, 例: 64)",
+let jobCounter = 0;
+const newJobId = () => `${Date.now()}-${++jobCounter}`;
+
+const paramHint = (param) => {
+  let range =
+    param.type === "choice"
+      ? param.options.join(" / ")
+      : param.type === "matrix"
+      ? "行间用 ; 分隔, 如 0 -1 0; -1 5 -1; 0 -1 0"
+      : `${param.min ?? "-∞"} ~ ${param.max ?? "∞"}`;
+  return param.default === null
+    ? `必填 (${range})`
+    : `默认: ${param.default} (${range})`;
 };
 
 function TransformDialog({
@@ -125,9 +118,17 @@ function TransformDialog({
   const [selectedImg2Data, setSelectedImg2Data] = useState(null);
 
   const [transformedImg, setTransformedImg] = useState(null);
-  const [transformArg, setTransformArg] = useState("");
+  const [transformSchemas, setTransformSchemas] = useState({});
+  const [transformArgs, setTransformArgs] = useState({});
   const [transformedName, setTransformedName] = useState("");
   const [inProgress, setInProgress] = useState(false);
+  const [jobId, setJobId] = useState(null);
+  const [progress, setProgress] = useState(null);
+  const [measurements, setMeasurements] = useState({});
+  // The backend keeps every result until it is released, so previews that
+  // are replaced or closed without confirming are freed here
+  const previewRef = useRef(null);
+  const activeJobRef = useRef(null);
   const [historyList, setHistoryList] = useState([
     {
       label: "...",
@@ -156,26 +157,71 @@ function TransformDialog({
     setLayoutRef(tabsLayoutRef);
   }, [tabsLayoutRef]);
 
-  const doTransform = async () => {
-    let img = selectedTabNode.getConfig().data;
-    let img2 = selectedImg2Data;
-    invoke("transform_image", {
-      img,
-      img2: img2 || "",
-      transform,
-      transformArg,
-    })
-      .then((imgBase64) => {
-        if (imgBase64) {
-          setTransformedImg(imgBase64);
-        }
+  useEffect(() => {
+    invoke("list_transforms")
+      .then((list) => {
+        setTransformSchemas(Object.fromEntries(list.map((t) => [t.name, t])));
       })
       .catch((e) => {
         console.error(e);
-      })
-      .finally(() => {
-        setInProgress(false);
       });
+  }, []);
+
+  const params = transformSchemas[transform]?.params || [];
+  const needsImg2 = transformSchemas[transform]?.second_image || false;
+
+  const releasePreview = () => {
+    if (previewRef.current !== null) {
+      invoke("release_image", { img: previewRef.current }).catch((e) => {
+        console.error(e);
+      });
+      previewRef.current = null;
+    }
+  };
+
+  const doTransform = async () => {
+    let img = selectedTabNode.getConfig().data.id;
+    let img2 = needsImg2 && selectedImg2Data ? selectedImg2Data.id : null;
+    let args = Object.fromEntries(
+      Object.entries(transformArgs).filter(([, value]) => value !== "")
+    );
+    let id = newJobId();
+    activeJobRef.current = id;
+    setJobId(id);
+    setProgress(null);
+    setMeasurements({});
+    // Listening before the job starts, so no progress event is missed
+    const unlisten = await listen("transform_progress", (event) => {
+      if (event.payload.job_id === id) {
+        setProgress(event.payload);
+      }
+    });
+    try {
+      let { measurements, ...image } = await invoke("transform_image", {
+        jobId: id,
+        img,
+        img2,
+        transform,
+        args,
+      });
+      if (activeJobRef.current !== id) {
+        // Finished after the dialog was closed
+        invoke("release_image", { img: image.id }).catch(() => {});
+        return;
+      }
+      releasePreview();
+      previewRef.current = image.id;
+      setTransformedImg(image);
+      setMeasurements(measurements);
+    } catch (e) {
+      if (e.code !== "cancelled") {
+        console.error(e);
+      }
+    } finally {
+      unlisten();
+      setJobId(null);
+      setInProgress(false);
+    }
   };
 
   useEffect(() => {
@@ -188,7 +234,16 @@ function TransformDialog({
     setInProgress(true);
   };
 
+  const cancelTransform = () => {
+    if (jobId !== null) {
+      // The job may have just finished, in which case there is nothing to stop
+      invoke("cancel_transform", { jobId }).catch(() => {});
+    }
+  };
+
   const confirm = async () => {
+    // Kept, as the new tab and history node refer to it
+    previewRef.current = null;
     let nodeConfig = selectedTabNode.getConfig();
     let targetTabset =
       tabsModel.getActiveTabset() || tabsModel.getFirstTabset();
@@ -222,9 +277,12 @@ function TransformDialog({
   };
 
   const close = async () => {
+    cancelTransform();
+    activeJobRef.current = null;
+    releasePreview();
     setTransformedImg(null);
     setTransformedName("");
-    setTransformArg("");
+    setTransformArgs({});
     onDialogCloseCallback();
   };
 
@@ -244,12 +302,7 @@ function TransformDialog({
                 mah={"95%"}
                 maw={"95%"}
                 fit="contain"
-                src={
-                  selectedTabNode
-                    ? `data:image/bmp;base64,${selectedTabNode.getConfig().data
-                    }`
-                    : null
-                }
+                src={imageSrc(selectedTabNode.getConfig().data, 192)}
               />
             ) : (
               <Stack align="center" gap={"xs"}>
@@ -260,14 +313,14 @@ function TransformDialog({
               </Stack>
             )}
           </Center>
-          {transform && transform.startsWith("binary_op") && (
+          {needsImg2 && (
             <Center h={192} w={192} bg={"#eeeeee"}>
               {selectedImg2Data ? (
                 <Image
                   mah={"95%"}
                   maw={"95%"}
                   fit="contain"
-                  src={`data:image/bmp;base64,${selectedImg2Data}`}
+                  src={imageSrc(selectedImg2Data, 192)}
                 />
               ) : (
                 <Stack align="center" gap={"xs"}>
@@ -284,13 +337,22 @@ function TransformDialog({
           </Center>
           <Center h={192} w={192} bg={"#eeeeee"}>
             {inProgress ? (
-              <Loader color="gray" />
+              <Stack align="center" gap={"xs"}>
+                <Loader color="gray" />
+                {progress && (
+                  <Text size="sm" c="dimmed">
+                    {`步骤 ${progress.step}: ${Math.floor(
+                      (progress.done * 100) / progress.total
+                    )}%`}
+                  </Text>
+                )}
+              </Stack>
             ) : transformedImg ? (
               <Image
                 mah={"95%"}
                 maw={"95%"}
                 fit="contain"
-                src={`data:image/bmp;base64,${transformedImg}`}
+                src={imageSrc(transformedImg, 192)}
               />
             ) : (
               <Stack align="center" gap={"xs"}>
@@ -302,17 +364,49 @@ function TransformDialog({
             )}
           </Center>
         </Group>
+        {Object.keys(measurements).length > 0 && (
+          <Text size="sm" c="dimmed">
+            {Object.entries(measurements)
+              .map(([name, value]) => `${name} = ${value.toFixed(3)}`)
+              .join(", ")}
+          </Text>
+        )}
         <Group w="100%" justify="center" align="center">
-          <TextInput
-            label="变换参数"
-            w="100%"
-            value={transformArg}
-            placeholder={argHintMap[transform]}
-            disabled={argHintMap[transform] === "无需参数"}
-            onChange={(e) => {
-              setTransformArg(e.target.value);
-            }}
-          />
+          {params.map((param) =>
+            param.type === "choice" ? (
+              <NativeSelect
+                key={param.name}
+                label={param.name}
+                w="100%"
+                value={transformArgs[param.name] ?? param.default ?? ""}
+                data={
+                  param.default === null
+                    ? ["", ...param.options]
+                    : param.options
+                }
+                onChange={(e) => {
+                  setTransformArgs({
+                    ...transformArgs,
+                    [param.name]: e.currentTarget.value,
+                  });
+                }}
+              />
+            ) : (
+              <TextInput
+                key={param.name}
+                label={param.name}
+                w="100%"
+                value={transformArgs[param.name] ?? ""}
+                placeholder={paramHint(param)}
+                onChange={(e) => {
+                  setTransformArgs({
+                    ...transformArgs,
+                    [param.name]: e.target.value,
+                  });
+                }}
+              />
+            )
+          )}
           <NativeSelect
             label="输入图像"
             w="100%"
@@ -332,7 +426,7 @@ function TransformDialog({
             value={selectedTabNode ? selectedTabNode.getId() : "..."}
             data={historyList}
           />
-          {transform && transform.startsWith("binary_op") && (
+          {needsImg2 && (
             <NativeSelect
               label="输入另一图像"
               w="100%"
@@ -368,9 +462,11 @@ function TransformDialog({
             <Button
               fullWidth
               size="sm"
-              onClick={selectedTabNode ? apply : null}
+              onClick={
+                inProgress ? cancelTransform : selectedTabNode ? apply : null
+              }
             >
-              应用
+              {inProgress ? "停止" : "应用"}
             </Button>
           </Grid.Col>
           <Grid.Col span={2}>
@@ -380,9 +476,7 @@ function TransformDialog({
               disabled={
                 inProgress ||
                 transformedImg === null ||
-                (transform &&
-                  transform.startsWith("binary_op") &&
-                  selectedImg2Id === null)
+                (needsImg2 && selectedImg2Id === null)
               }
               onClick={confirm}
             >
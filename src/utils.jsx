@@ -1,3 +1,5 @@
+import { convertFileSrc } from "@tauri-apps/api/tauri";
+
 function searchTreeNodeById(tree, id) {
   if (tree.id === id) {
     return tree;
@@ -69,7 +71,15 @@ const extractHistories = (trees) => {
   return histories;
 };
 
+// Images live in the backend; `image` is the handle returned by
+// `open_image`/`transform_image`. Pass `size` to get a downscaled preview.
+const imageSrc = (image, size) => {
+  let src = convertFileSrc(`${image.id}`, "dip");
+  return size ? `${src}?size=${size}` : src;
+};
+
 export {
+  imageSrc,
   searchTreeNodeById,
   searchTreeNodeByIdAsync,
   extractHistories,